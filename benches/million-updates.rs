@@ -17,7 +17,7 @@ fn million_vivaldi_2d_updates() {
             if i == j {
                 continue;
             }
-            let rtt = if i > j { i - j } else { j - i };
+            let rtt = i.abs_diff(j);
             // let nc_j = nc[j].clone();
             nc_j.update(&nc_i, Duration::from_millis(rtt.try_into().unwrap()));
         }
@@ -36,7 +36,7 @@ fn million_vivaldi_3d_updates() {
             if i == j {
                 continue;
             }
-            let rtt = if i > j { i - j } else { j - i };
+            let rtt = i.abs_diff(j);
             // let nc_j = nc[j].clone();
             nc_j.update(&nc_i, Duration::from_millis(rtt.try_into().unwrap()));
         }
@@ -1,58 +1,25 @@
 // this uses a copy of data from https://github.com/uofa-rzhu3/NetLatency-Data
 //
 // for info on the format, see `NetLatency-Data/README.md`
-
-//
-// **** Features ****
 //
+// run with `cargo run --features datasets --example planetlab`
 
-/// `FloatType` is a type alias for either `f32` or `f64` depending on cargo features
-#[cfg(feature = "f32")]
-type FloatType = f32;
-
-/// `FloatType` is a type alias for either `f32` or `f64` depending on cargo features
-#[cfg(not(feature = "f32"))]
-type FloatType = f64;
-
-use std::{
-    fs::File,
-    io::{BufReader, Read},
-    time::Duration,
+use vivaldi_nc::{
+    datasets::{DatasetFormat, LatencyMatrix},
+    simulator::{RandomPeer, Simulator},
 };
 
-use rand::Rng;
-use vivaldi_nc::NetworkCoordinate;
-
-// this will return a Vec<Vec<Duration>>
-fn load_planetlab_file(filename: &str) -> Vec<Vec<Duration>> {
-    // we're expecting 490 lines, each with 490 numbers representing ms
-    let f = File::open(filename).expect("unable to open file");
-    let mut str = String::new();
-    BufReader::new(f)
-        .read_to_string(&mut str)
-        .expect("unable to read file");
-
-    str.lines()
-        .map(|line| {
-            // ...
-            line.split_whitespace()
-                .map(|token| {
-                    Duration::from_secs_f32(token.parse::<f32>().expect("parse error") / 1000.0)
-                })
-                .collect()
-        })
-        .collect()
-}
-
 const NUM_LATENCY_SETS: usize = 18;
 const NUM_NODES: usize = 490;
+const MAX_ROUNDS: usize = 100;
 
-fn load_data() -> Vec<Vec<Vec<Duration>>> {
+fn load_data() -> Vec<LatencyMatrix> {
     // our input filenames (note naming is 1-based, not 0-based)
     (1..=NUM_LATENCY_SETS)
         .map(|i| {
             let filename = format!("examples/NetLatency-Data/PlanetLab/PlanetLabData_{i}");
-            load_planetlab_file(&filename)
+            LatencyMatrix::load(&filename, DatasetFormat::PlanetLab)
+                .unwrap_or_else(|e| panic!("unable to load {filename}: {e}"))
         })
         .collect()
 }
@@ -63,49 +30,34 @@ fn main() {
 
     // make check to see that we got NUM_NODES worth of data
     assert_eq!(data.len(), NUM_LATENCY_SETS);
-    assert_eq!(data[0].len(), NUM_NODES);
-    assert_eq!(data[0][0].len(), NUM_NODES);
-
-    // create our network coordinates
-    let mut nc: Vec<NetworkCoordinate<3>> = (0..NUM_NODES)
-        .map(|_| NetworkCoordinate::<3>::new())
-        .collect();
-
-    // the main loop:
-    // 1. choose a local and remote NC
-    // 2. choose one of the 18 latency sample sets
-    // 3. lookup the latency in our data
-    // 4. adjust local NC
-    let mut error = 0.0;
-    for _ in 0..30_000 {
-        let mut rng = rand::thread_rng();
-        // 1. choose a local and remote NC
-        let i_local = rng.gen_range(0..NUM_NODES);
-        let i_remote = rng.gen_range(0..NUM_NODES);
-        if i_local == i_remote {
-            continue;
-        }
-
-        // 2. choose one of the 18 latency sample sets
-        let i_set = 0; //rng.generate::<usize>() % NUM_LATENCY_SETS;
-
-        // 3. lookup the latency in our data
-        let rtt_measured = data[i_set][i_local][i_remote];
-
-        // 4. adjust local NC
-        let nc_remote = nc[i_remote].clone();
-        nc[i_local].update(&nc_remote, rtt_measured);
-
-        // complete, now let's sum up the error
-        error = nc.iter().map(|n| n.error()).sum::<FloatType>() / NUM_NODES as FloatType;
-        assert!(error.is_finite());
-        if error < 5.0 {
-            break;
-        }
-    }
-    assert!(error < 5.0);
+    assert_eq!(data[0].dimensions(), (NUM_NODES, NUM_NODES));
+
+    // the main loop: every round, each node measures a random peer in the current latency set
+    // (the sets are snapshots of the same network at different times, so the simulator cycles
+    // through them, one per round) and adjusts its NC, until the average error is low enough
+    let mut simulator = Simulator::<3, _>::new(data, RandomPeer, rand::random());
+    let stats = simulator
+        .rounds()
+        .take(MAX_ROUNDS)
+        .inspect(|stats| assert!(stats.mean_error.is_finite()))
+        .find(|stats| stats.mean_error < 5.0);
+    assert!(stats.is_some());
+
+    // report how well the coordinates actually predict the RTTs of the first latency set
+    let report = simulator.accuracy().expect("no measured RTTs");
+    eprintln!(
+        "median relative error {:.3}, 90th percentile {:.3}, RELA {:.3}, stress {:.3}, \
+         closest neighbor loss {:?} ({:.0}% correct)",
+        report.median_relative_error(),
+        report.percentile_relative_error(0.9),
+        report.rela(),
+        report.stress(),
+        report.closest_neighbor_loss(),
+        report.closest_neighbor_accuracy() * 100.0
+    );
 
     // output the NC array as JSON
-    let json = serde_json::to_string_pretty(&nc).expect("JSON serialization error");
+    let json =
+        serde_json::to_string_pretty(simulator.coordinates()).expect("JSON serialization error");
     println!("{json}");
 }
@@ -0,0 +1,455 @@
+// Runs Vivaldi over a captured RTT matrix and reports how accurate the resulting coordinates are.
+//
+// Needs the `cli` feature, e.g.:
+//
+//     cargo run --features cli --bin vivaldi-sim -- --dimensions 3 \
+//         examples/NetLatency-Data/PlanetLab/PlanetLabData_1
+//
+// Run with `--help` for all options.
+
+use std::{
+    env, fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use serde_json::{json, Value};
+use vivaldi_nc::{
+    datasets::{DatasetFormat, LatencyMatrix},
+    metrics::AccuracyReport,
+    simulator::{RandomPeer, Simulator},
+    NetworkCoordinate, UpdateRule, VivaldiConfig,
+};
+
+const USAGE: &str = "\
+Usage: vivaldi-sim [OPTIONS] <PATH>
+
+Simulates Vivaldi over the RTT matrix in PATH and prints the final coordinates and an accuracy
+report. If PATH is a directory, every file in it is loaded as a time slice of the same network,
+and the simulation cycles through them, one slice per round (in name order, shorter names first,
+so `_2` comes before `_10`). Accuracy is always measured against the first slice.
+
+Options:
+  --format <FORMAT>            matrix format, `planetlab` (ms) or `seattle` (s) [default: planetlab]
+  --dimensions <N>             dimensions of the coordinates, 1 to 8 [default: 3]
+  --rounds <ROUNDS>            number of rounds, each node measures one random peer per round
+                               [default: 200]
+  --seed <SEED>                seed for all randomness [default: 0]
+  --c-error <C_ERROR>          error moving average weight, in (0, 1]
+  --c-delta <C_DELTA>          timestep weight, in (0, 1]
+  --update-rule <RULE>         `constant`, `adaptive`, or `local_error` [default: adaptive]
+  --adjustment-window <SIZE>   enable the Serf style adjustment term with this window size
+  --gravity-rho <RHO>          enable gravity toward the origin with this strength
+  --output <OUTPUT>            `json` or `csv` [default: json]
+  -h, --help                   print this help
+";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Output {
+    Json,
+    Csv,
+}
+
+struct Options {
+    path: PathBuf,
+    format: DatasetFormat,
+    dimensions: usize,
+    rounds: usize,
+    seed: u64,
+    config: VivaldiConfig,
+    output: Output,
+}
+
+fn parse<T: std::str::FromStr>(name: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("missing value for `{name}`"))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value `{value}` for `{name}`"))
+}
+
+// Ok(None) means help was requested
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut path = None;
+    let mut format = DatasetFormat::PlanetLab;
+    let mut dimensions = 3;
+    let mut rounds = 200;
+    let mut seed = 0;
+    let mut output = Output::Json;
+    let mut builder = VivaldiConfig::<f64>::builder();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--format" => {
+                format = match parse::<String>(&arg, args.next())?.as_str() {
+                    "planetlab" => DatasetFormat::PlanetLab,
+                    "seattle" => DatasetFormat::Seattle,
+                    other => return Err(format!("unknown format `{other}`")),
+                }
+            }
+            "--dimensions" => dimensions = parse(&arg, args.next())?,
+            "--rounds" => rounds = parse(&arg, args.next())?,
+            "--seed" => seed = parse(&arg, args.next())?,
+            "--c-error" => builder = builder.c_error(parse(&arg, args.next())?),
+            "--c-delta" => builder = builder.c_delta(parse(&arg, args.next())?),
+            "--update-rule" => {
+                builder = builder.update_rule(match parse::<String>(&arg, args.next())?.as_str() {
+                    "constant" => UpdateRule::Constant,
+                    "adaptive" => UpdateRule::Adaptive,
+                    "local_error" => UpdateRule::LocalError,
+                    other => return Err(format!("unknown update rule `{other}`")),
+                });
+            }
+            "--adjustment-window" => {
+                builder = builder.adjustment_window(parse(&arg, args.next())?);
+            }
+            "--gravity-rho" => builder = builder.gravity_rho(Some(parse(&arg, args.next())?)),
+            "--output" => {
+                output = match parse::<String>(&arg, args.next())?.as_str() {
+                    "json" => Output::Json,
+                    "csv" => Output::Csv,
+                    other => return Err(format!("unknown output `{other}`")),
+                }
+            }
+            other if other.starts_with('-') => return Err(format!("unknown option `{other}`")),
+            other if path.is_none() => path = Some(PathBuf::from(other)),
+            other => return Err(format!("unexpected argument `{other}`")),
+        }
+    }
+
+    if !(1..=8).contains(&dimensions) {
+        return Err(format!(
+            "unsupported number of dimensions `{dimensions}`, expected 1 to 8"
+        ));
+    }
+
+    Ok(Some(Options {
+        path: path.ok_or("missing PATH")?,
+        format,
+        dimensions,
+        rounds,
+        seed,
+        config: builder.build().map_err(|e| e.to_string())?,
+        output,
+    }))
+}
+
+// loads a single matrix, or all matrices in a directory
+fn load(path: &Path, format: DatasetFormat) -> Result<Vec<LatencyMatrix>, String> {
+    let load_file = |path: &Path| {
+        LatencyMatrix::load(path, format).map_err(|e| format!("{}: {e}", path.display()))
+    };
+    if !path.is_dir() {
+        return Ok(vec![load_file(path)?]);
+    }
+
+    let mut files = fs::read_dir(path)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    files.retain(|file| file.is_file());
+    files.sort_by_key(|file| (file.as_os_str().len(), file.clone()));
+
+    let matrices = files
+        .iter()
+        .map(|file| load_file(file))
+        .collect::<Result<Vec<_>, _>>()?;
+    let size = matrices
+        .first()
+        .ok_or_else(|| format!("{}: no files", path.display()))?
+        .size();
+    if let Some((file, _)) = files
+        .iter()
+        .zip(&matrices)
+        .find(|(_, matrix)| matrix.size() != size)
+    {
+        return Err(format!(
+            "{}: expected {size} nodes like the other files",
+            file.display()
+        ));
+    }
+    Ok(matrices)
+}
+
+fn simulate<const N: usize>(options: &Options, matrices: Vec<LatencyMatrix>) -> Result<(), String> {
+    // the matrices of a directory are time slices of the same network, cycled one per round
+    let mut simulator =
+        Simulator::<N, _>::new_with_config(matrices, RandomPeer, options.seed, options.config);
+    simulator.run(options.rounds);
+
+    let coordinates = simulator.coordinates();
+    let report = AccuracyReport::new(coordinates, &simulator.source()[0])
+        .ok_or("matrix has no measured RTTs")?;
+
+    let metrics = [
+        ("rounds", json!(simulator.round())),
+        ("pairs", json!(report.pairs())),
+        ("mean_relative_error", json!(report.mean_relative_error())),
+        (
+            "median_relative_error",
+            json!(report.median_relative_error()),
+        ),
+        (
+            "p90_relative_error",
+            json!(report.percentile_relative_error(0.9)),
+        ),
+        ("rela", json!(report.rela())),
+        ("stress", json!(report.stress())),
+        (
+            "closest_neighbor_loss_ms",
+            json!(report.closest_neighbor_loss().as_secs_f64() * 1000.0),
+        ),
+        (
+            "closest_neighbor_accuracy",
+            json!(report.closest_neighbor_accuracy()),
+        ),
+    ];
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let written = match options.output {
+        Output::Json => write_json(&mut out, &metrics, coordinates),
+        Output::Csv => write_csv(&mut out, &metrics, coordinates),
+    };
+    match written.and_then(|()| out.flush()) {
+        // e.g. piped into `head`
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        written => written.map_err(|e| e.to_string()),
+    }
+}
+
+fn write_json<const N: usize>(
+    out: &mut impl Write,
+    metrics: &[(&str, Value)],
+    coordinates: &[NetworkCoordinate<N>],
+) -> io::Result<()> {
+    let accuracy: serde_json::Map<_, _> = metrics
+        .iter()
+        .map(|(name, value)| ((*name).to_string(), value.clone()))
+        .collect();
+    let json = json!({ "accuracy": accuracy, "coordinates": coordinates });
+    serde_json::to_writer_pretty(&mut *out, &json)?;
+    writeln!(out)
+}
+
+// the metrics, a blank line, then one row per node with the fields of its serialized form, where
+// the adjustment is empty unless it's enabled
+fn write_csv<const N: usize>(
+    out: &mut impl Write,
+    metrics: &[(&str, Value)],
+    coordinates: &[NetworkCoordinate<N>],
+) -> io::Result<()> {
+    writeln!(out, "metric,value")?;
+    for (name, value) in metrics {
+        writeln!(out, "{name},{value}")?;
+    }
+    writeln!(out)?;
+
+    let position: Vec<String> = (0..N).map(|i| format!("position_{i}")).collect();
+    writeln!(out, "node,{},height,error,adjustment", position.join(","))?;
+    for (node, nc) in coordinates.iter().enumerate() {
+        let value = serde_json::to_value(nc)?;
+        let position: Vec<String> = value["position"]
+            .as_array()
+            .map(|position| position.iter().map(ToString::to_string).collect())
+            .unwrap_or_default();
+        let adjustment = value
+            .get("adjustment")
+            .map_or_else(String::new, ToString::to_string);
+        writeln!(
+            out,
+            "{node},{},{},{},{adjustment}",
+            position.join(","),
+            value["height"],
+            value["error"]
+        )?;
+    }
+    Ok(())
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let matrices = load(&options.path, options.format)?;
+    match options.dimensions {
+        1 => simulate::<1>(options, matrices),
+        2 => simulate::<2>(options, matrices),
+        3 => simulate::<3>(options, matrices),
+        4 => simulate::<4>(options, matrices),
+        5 => simulate::<5>(options, matrices),
+        6 => simulate::<6>(options, matrices),
+        7 => simulate::<7>(options, matrices),
+        8 => simulate::<8>(options, matrices),
+        n => unreachable!("dimensions are checked while parsing arguments, got {n}"),
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+//
+// **** Tests ****
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Result<Option<Options>, String> {
+        parse_args(args.split_whitespace().map(String::from))
+    }
+
+    fn nc(json: &str) -> NetworkCoordinate<2> {
+        serde_json::from_str(json).expect("deserialization failed during test")
+    }
+
+    fn metrics() -> Vec<(&'static str, Value)> {
+        vec![("rounds", json!(3)), ("rela", json!(0.25))]
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = args(
+            "--format seattle --dimensions 2 --rounds 50 --seed 7 --c-error 0.5 --c-delta 0.1 \
+             --update-rule local_error --adjustment-window 20 --gravity-rho 150 --output csv data",
+        )
+        .expect("valid arguments rejected during test")
+        .expect("help printed during test");
+        assert_eq!(options.path, PathBuf::from("data"));
+        assert_eq!(options.format, DatasetFormat::Seattle);
+        assert_eq!(options.dimensions, 2);
+        assert_eq!(options.rounds, 50);
+        assert_eq!(options.seed, 7);
+        assert_eq!(options.output, Output::Csv);
+        let expected = VivaldiConfig::builder()
+            .c_error(0.5)
+            .c_delta(0.1)
+            .update_rule(UpdateRule::LocalError)
+            .adjustment_window(20)
+            .gravity_rho(Some(150.0))
+            .build();
+        assert_eq!(Ok(options.config), expected);
+
+        // the defaults
+        let options = args("data")
+            .expect("valid arguments rejected during test")
+            .expect("help printed during test");
+        assert_eq!(options.format, DatasetFormat::PlanetLab);
+        assert_eq!(options.dimensions, 3);
+        assert_eq!(options.rounds, 200);
+        assert_eq!(options.seed, 0);
+        assert_eq!(options.output, Output::Json);
+        assert_eq!(options.config, VivaldiConfig::default());
+
+        assert!(matches!(args("data --help"), Ok(None)));
+    }
+
+    #[test]
+    fn test_parse_args_invalid() {
+        let error = |a| args(a).err();
+        assert_eq!(error(""), Some("missing PATH".to_string()));
+        assert_eq!(
+            error("data --rounds"),
+            Some("missing value for `--rounds`".to_string())
+        );
+        assert_eq!(
+            error("--rounds many data"),
+            Some("invalid value `many` for `--rounds`".to_string())
+        );
+        assert_eq!(
+            error("--bogus data"),
+            Some("unknown option `--bogus`".to_string())
+        );
+        assert_eq!(
+            error("data more"),
+            Some("unexpected argument `more`".to_string())
+        );
+        assert_eq!(
+            error("--format csv data"),
+            Some("unknown format `csv`".to_string())
+        );
+        assert_eq!(
+            error("--update-rule fast data"),
+            Some("unknown update rule `fast`".to_string())
+        );
+        assert_eq!(
+            error("--output xml data"),
+            Some("unknown output `xml`".to_string())
+        );
+        assert_eq!(
+            error("--dimensions 9 data"),
+            Some("unsupported number of dimensions `9`, expected 1 to 8".to_string())
+        );
+        assert_eq!(
+            error("--c-error 2 data"),
+            Some("`c_error` must be in (0, 1]".to_string())
+        );
+    }
+
+    #[test]
+    fn test_write_json() {
+        let coordinates = [
+            nc("{\"position\":[1.5,0.5],\"height\":0.1,\"error\":1.0}"),
+            nc("{\"position\":[-2.0,3.0],\"height\":0.5,\"error\":0.2,\"adjustment\":-0.25}"),
+        ];
+        let mut out = Vec::new();
+        write_json(&mut out, &metrics(), &coordinates).expect("write failed during test");
+
+        let json: Value = serde_json::from_slice(&out).expect("invalid JSON written during test");
+        assert_eq!(json["accuracy"]["rounds"], json!(3));
+        assert_eq!(json["accuracy"]["rela"], json!(0.25));
+
+        // the coordinates use their usual serialized form, so they read back as coordinates
+        let read: Vec<NetworkCoordinate<2>> = serde_json::from_value(json["coordinates"].clone())
+            .expect("deserialization failed during test");
+        assert_eq!(read.len(), coordinates.len());
+        for (read, written) in read.iter().zip(&coordinates) {
+            assert_eq!(
+                serde_json::to_value(read).ok(),
+                serde_json::to_value(written).ok()
+            );
+        }
+        assert_eq!(read[1].adjustment(), Some(-0.25));
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let coordinates = [
+            nc("{\"position\":[1.5,0.5],\"height\":0.1,\"error\":1.0}"),
+            nc("{\"position\":[-2.0,3.0],\"height\":0.5,\"error\":0.2,\"adjustment\":-0.25}"),
+        ];
+        let mut out = Vec::new();
+        write_csv(&mut out, &metrics(), &coordinates).expect("write failed during test");
+        assert_eq!(
+            String::from_utf8(out).ok(),
+            Some(
+                "metric,value\n\
+                 rounds,3\n\
+                 rela,0.25\n\
+                 \n\
+                 node,position_0,position_1,height,error,adjustment\n\
+                 0,1.5,0.5,0.1,1.0,\n\
+                 1,-2.0,3.0,0.5,0.2,-0.25\n"
+                    .to_string()
+            )
+        );
+    }
+}
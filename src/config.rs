@@ -0,0 +1,498 @@
+//! Runtime tuning parameters for the Vivaldi algorithm.
+//!
+//! The defaults match the values recommended by the original paper, and are what
+//! [`NetworkCoordinate::new`](crate::NetworkCoordinate::new) and
+//! [`NetworkCoordinate::update`](crate::NetworkCoordinate::update) use. Deployments which need to
+//! trade convergence speed against stability can build their own [`VivaldiConfig`] and use it with
+//! [`NetworkCoordinate::new_with_config`](crate::NetworkCoordinate::new_with_config) and
+//! [`NetworkCoordinate::update_with_config`](crate::NetworkCoordinate::update_with_config).
+//!
+//! # Example
+//!
+//! ```
+//! use vivaldi_nc::VivaldiConfig;
+//!
+//! // converge a little faster than the default, at the expense of some stability
+//! let config: VivaldiConfig = VivaldiConfig::builder().c_delta(0.5).build().unwrap();
+//! assert!((config.c_delta() - 0.5).abs() < 1e-6);
+//!
+//! // invalid parameters are rejected
+//! assert!(VivaldiConfig::<f64>::builder().c_error(-1.0).build().is_err());
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::ConfigError;
+use crate::real::Real;
+
+//
+// **** Constants ****
+//
+
+// Vivaldi tuning parameters
+const C_ERROR: f64 = 0.25;
+const C_DELTA: f64 = 0.25;
+
+// initial error value
+const DEFAULT_ERROR: f64 = 200.0;
+
+// NOTE: error should always be greater than zero, so the minimum error defaults to the machine
+//       epsilon of whichever float type is used
+
+// height should always be positive, so it can be scaled up or down (10µs, same as Serf)
+const MIN_HEIGHT: f64 = 0.01;
+
+//
+// **** Enums ****
+//
+
+/// The rule used to compute the timestep `δ`, i.e. how far a single sample moves a coordinate.
+///
+/// These are the variants evaluated in section 4 of the Vivaldi paper. Only the timestep
+/// differs between them, the error estimate is always updated the same way. Since a
+/// [`VivaldiConfig`] is passed to each update, every coordinate can use its own rule.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateRule {
+    /// `δ = cc`, a constant timestep. Small values converge slowly, large values oscillate.
+    Constant,
+
+    /// `δ = cc × ei / (ei + ej)`, the adaptive timestep the paper settles on. A node with low
+    /// error moves less, and a sample from a node with high error counts for less.
+    #[default]
+    Adaptive,
+
+    /// `δ = cc × min(ei, 1)`, a timestep weighted by the local (relative) error only, which
+    /// ignores how confident the remote node is.
+    ///
+    /// The clamp to 1 isn't from the paper. It's a safeguard of this crate, since the error starts
+    /// out far above 1 (see [`VivaldiConfig::default_error`]), and an unclamped timestep would
+    /// throw a new coordinate many times past the measured RTT.
+    LocalError,
+}
+
+//
+// **** Structs ****
+//
+
+/// Tuning parameters for Vivaldi coordinate updates.
+///
+/// A `VivaldiConfig` can only be created through [`VivaldiConfig::default`] or
+/// [`VivaldiConfig::builder`], both of which guarantee every parameter is in range. The same
+/// validation is applied when deserializing, so a config loaded from a file is always usable.
+///
+/// ## Generic Parameters
+///
+/// - `T`: the floating point type of the coordinates this config is used with, `f64` by default
+// NOTE: `Eq` would only be derived for `T: Eq`, which no `Real` is
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    try_from = "VivaldiConfigBuilder<T>",
+    bound(serialize = "T: Real", deserialize = "T: Real")
+)]
+pub struct VivaldiConfig<T = f64>
+where
+    T: Real,
+{
+    c_error: T,
+    c_delta: T,
+    default_error: T,
+    min_error: T,
+    min_height: T,
+    adjustment_window: usize,
+    gravity_rho: Option<T>,
+    update_rule: UpdateRule,
+}
+
+/// Builder for a validated [`VivaldiConfig`].
+///
+/// Every parameter starts at its default value, so only the ones being tuned need to be set.
+// NOTE: `Eq` would only be derived for `T: Eq`, which no `Real` is
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, bound(serialize = "T: Real", deserialize = "T: Real"))]
+pub struct VivaldiConfigBuilder<T = f64>
+where
+    T: Real,
+{
+    c_error: T,
+    c_delta: T,
+    default_error: T,
+    min_error: T,
+    min_height: T,
+    adjustment_window: usize,
+    gravity_rho: Option<T>,
+    update_rule: UpdateRule,
+}
+
+//
+// **** Implementations ****
+//
+
+impl<T> VivaldiConfig<T>
+where
+    T: Real,
+{
+    /// Creates a [`VivaldiConfigBuilder`] with every parameter set to its default.
+    #[must_use]
+    pub fn builder() -> VivaldiConfigBuilder<T> {
+        // the defaults are only spelled out in `Default`
+        let Self {
+            c_error,
+            c_delta,
+            default_error,
+            min_error,
+            min_height,
+            adjustment_window,
+            gravity_rho,
+            update_rule,
+        } = Self::default();
+        VivaldiConfigBuilder {
+            c_error,
+            c_delta,
+            default_error,
+            min_error,
+            min_height,
+            adjustment_window,
+            gravity_rho,
+            update_rule,
+        }
+    }
+
+    /// `ce` from the paper: how strongly each sample moves the local error estimate.
+    #[must_use]
+    pub const fn c_error(&self) -> T {
+        self.c_error
+    }
+
+    /// `cc` from the paper: how far each sample moves the local coordinate.
+    #[must_use]
+    pub const fn c_delta(&self) -> T {
+        self.c_delta
+    }
+
+    /// The error estimate given to a newly created coordinate.
+    #[must_use]
+    pub const fn default_error(&self) -> T {
+        self.default_error
+    }
+
+    /// The lower bound for a coordinate's error estimate.
+    #[must_use]
+    pub const fn min_error(&self) -> T {
+        self.min_error
+    }
+
+    /// The lower bound for a coordinate's height, in milliseconds.
+    #[must_use]
+    pub const fn min_height(&self) -> T {
+        self.min_height
+    }
+
+    /// How many recent residuals are averaged into the adjustment term, zero when disabled.
+    #[must_use]
+    pub const fn adjustment_window(&self) -> usize {
+        self.adjustment_window
+    }
+
+    /// The strength of the gravity pulling coordinates toward the origin, `None` when disabled.
+    #[must_use]
+    pub const fn gravity_rho(&self) -> Option<T> {
+        self.gravity_rho
+    }
+
+    /// The rule used to compute the timestep of each update.
+    #[must_use]
+    pub const fn update_rule(&self) -> UpdateRule {
+        self.update_rule
+    }
+}
+
+impl<T> VivaldiConfigBuilder<T>
+where
+    T: Real,
+{
+    /// Sets `ce`, which must be in `(0, 1]`.
+    #[must_use]
+    pub const fn c_error(mut self, c_error: T) -> Self {
+        self.c_error = c_error;
+        self
+    }
+
+    /// Sets `cc`, which must be in `(0, 1]`.
+    #[must_use]
+    pub const fn c_delta(mut self, c_delta: T) -> Self {
+        self.c_delta = c_delta;
+        self
+    }
+
+    /// Sets the initial error estimate, which must be finite and at least `min_error`.
+    #[must_use]
+    pub const fn default_error(mut self, default_error: T) -> Self {
+        self.default_error = default_error;
+        self
+    }
+
+    /// Sets the lower bound for the error estimate, which must be finite and greater than zero.
+    #[must_use]
+    pub const fn min_error(mut self, min_error: T) -> Self {
+        self.min_error = min_error;
+        self
+    }
+
+    /// Sets the lower bound for the height in milliseconds, which must be finite and not negative.
+    #[must_use]
+    pub const fn min_height(mut self, min_height: T) -> Self {
+        self.min_height = min_height;
+        self
+    }
+
+    /// Enables the adjustment term used by Serf, by averaging this many recent residuals
+    /// (measured minus estimated RTT). The adjustment is added in
+    /// [`NetworkCoordinate::estimated_rtt`](crate::NetworkCoordinate::estimated_rtt) and corrects
+    /// for error the coordinate itself can't capture. Serf uses a window of 20. Zero, the default,
+    /// disables it.
+    #[must_use]
+    pub const fn adjustment_window(mut self, adjustment_window: usize) -> Self {
+        self.adjustment_window = adjustment_window;
+        self
+    }
+
+    /// Enables gravity toward the origin (from Ledlie et al., also used by Serf), which keeps the
+    /// whole coordinate system from slowly drifting away from the origin. Each update pulls the
+    /// position toward the origin by `(‖x‖ / rho)²` milliseconds, so `rho` must be finite and
+    /// greater than zero. Larger values mean weaker gravity, which is negligible for coordinates
+    /// well inside `rho`. Serf uses 150. `None`, the default, disables it.
+    #[must_use]
+    pub const fn gravity_rho(mut self, gravity_rho: Option<T>) -> Self {
+        self.gravity_rho = gravity_rho;
+        self
+    }
+
+    /// Sets the rule used to compute the timestep of each update. The default is
+    /// [`UpdateRule::Adaptive`].
+    #[must_use]
+    pub const fn update_rule(mut self, update_rule: UpdateRule) -> Self {
+        self.update_rule = update_rule;
+        self
+    }
+
+    /// Validates the parameters and builds the [`VivaldiConfig`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfigError`] naming the first parameter which is out of range.
+    pub fn build(self) -> Result<VivaldiConfig<T>, ConfigError> {
+        if !(self.c_error > T::zero() && self.c_error <= T::one()) {
+            return Err(ConfigError::OutOfRange {
+                parameter: "c_error",
+                expected: "in (0, 1]",
+            });
+        }
+        if !(self.c_delta > T::zero() && self.c_delta <= T::one()) {
+            return Err(ConfigError::OutOfRange {
+                parameter: "c_delta",
+                expected: "in (0, 1]",
+            });
+        }
+        if !(self.min_error.is_finite() && self.min_error > T::zero()) {
+            return Err(ConfigError::OutOfRange {
+                parameter: "min_error",
+                expected: "finite and greater than zero",
+            });
+        }
+        if !(self.default_error.is_finite() && self.default_error >= self.min_error) {
+            return Err(ConfigError::OutOfRange {
+                parameter: "default_error",
+                expected: "finite and at least `min_error`",
+            });
+        }
+        if !(self.min_height.is_finite() && self.min_height >= T::zero()) {
+            return Err(ConfigError::OutOfRange {
+                parameter: "min_height",
+                expected: "finite and not negative",
+            });
+        }
+        if let Some(rho) = self.gravity_rho {
+            if !(rho.is_finite() && rho > T::zero()) {
+                return Err(ConfigError::OutOfRange {
+                    parameter: "gravity_rho",
+                    expected: "finite and greater than zero",
+                });
+            }
+        }
+
+        Ok(VivaldiConfig {
+            c_error: self.c_error,
+            c_delta: self.c_delta,
+            default_error: self.default_error,
+            min_error: self.min_error,
+            min_height: self.min_height,
+            adjustment_window: self.adjustment_window,
+            gravity_rho: self.gravity_rho,
+            update_rule: self.update_rule,
+        })
+    }
+}
+
+//
+// **** Trait Implementations ****
+//
+
+impl UpdateRule {
+    /// Computes the timestep `δ` for a sample with weight `w = ei / (ei + ej)`.
+    pub(crate) fn delta<T: Real>(self, c_delta: T, local_error: T, w: T) -> T {
+        match self {
+            Self::Constant => c_delta,
+            Self::Adaptive => c_delta * w,
+            Self::LocalError => c_delta * local_error.min(T::one()),
+        }
+    }
+}
+
+impl<T> Default for VivaldiConfig<T>
+where
+    T: Real,
+{
+    /// The default config uses the tuning parameters recommended by the paper.
+    fn default() -> Self {
+        Self {
+            c_error: T::from_f64(C_ERROR),
+            c_delta: T::from_f64(C_DELTA),
+            default_error: T::from_f64(DEFAULT_ERROR),
+            min_error: T::epsilon(),
+            min_height: T::from_f64(MIN_HEIGHT),
+            adjustment_window: 0,
+            gravity_rho: None,
+            update_rule: UpdateRule::default(),
+        }
+    }
+}
+
+impl<T> Default for VivaldiConfigBuilder<T>
+where
+    T: Real,
+{
+    /// A default builder starts with the default parameters.
+    fn default() -> Self {
+        VivaldiConfig::builder()
+    }
+}
+
+impl<T> TryFrom<VivaldiConfigBuilder<T>> for VivaldiConfig<T>
+where
+    T: Real,
+{
+    type Error = ConfigError;
+
+    /// Validate a builder, used by `Deserialize`.
+    fn try_from(value: VivaldiConfigBuilder<T>) -> Result<Self, Self::Error> {
+        value.build()
+    }
+}
+
+//
+// **** Tests ****
+//
+#[cfg(test)]
+mod tests {
+    use assert_approx_eq::assert_approx_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_default() {
+        let a = VivaldiConfig::<f64>::default();
+        assert_approx_eq!(a.c_error(), 0.25);
+        assert_approx_eq!(a.c_delta(), 0.25);
+        assert_approx_eq!(a.default_error(), 200.0);
+        assert_approx_eq!(a.min_error(), f64::EPSILON);
+        assert_approx_eq!(a.min_height(), 0.01);
+        assert_eq!(a.adjustment_window(), 0);
+        assert_eq!(a.gravity_rho(), None);
+        assert_eq!(a.update_rule(), UpdateRule::Adaptive);
+        assert_eq!(VivaldiConfig::builder().build(), Ok(a));
+    }
+
+    #[test]
+    fn test_builder() {
+        let a = VivaldiConfig::<f64>::builder()
+            .c_error(0.1)
+            .c_delta(0.05)
+            .default_error(10.0)
+            .min_error(0.5)
+            .min_height(1.0)
+            .adjustment_window(20)
+            .gravity_rho(Some(150.0))
+            .update_rule(UpdateRule::Constant)
+            .build()
+            .expect("valid config rejected during test");
+        assert_approx_eq!(a.c_error(), 0.1);
+        assert_approx_eq!(a.c_delta(), 0.05);
+        assert_approx_eq!(a.default_error(), 10.0);
+        assert_approx_eq!(a.min_error(), 0.5);
+        assert_approx_eq!(a.min_height(), 1.0);
+        assert_eq!(a.adjustment_window(), 20);
+        assert_eq!(a.gravity_rho(), Some(150.0));
+        assert_eq!(a.update_rule(), UpdateRule::Constant);
+    }
+
+    #[test]
+    fn test_builder_invalid() {
+        let b = VivaldiConfig::<f64>::builder();
+        assert!(b.c_error(0.0).build().is_err());
+        assert!(b.c_error(1.5).build().is_err());
+        assert!(b.c_delta(f64::NAN).build().is_err());
+        assert!(b.min_error(0.0).build().is_err());
+        assert!(b.default_error(f64::INFINITY).build().is_err());
+        assert!(b.min_height(-1.0).build().is_err());
+        assert!(b.gravity_rho(Some(0.0)).build().is_err());
+        assert!(b.gravity_rho(Some(f64::INFINITY)).build().is_err());
+        assert_eq!(
+            b.min_error(2.0).default_error(1.0).build(),
+            Err(ConfigError::OutOfRange {
+                parameter: "default_error",
+                expected: "finite and at least `min_error`",
+            })
+        );
+    }
+
+    #[test]
+    fn test_update_rule_delta() {
+        assert_approx_eq!(UpdateRule::Constant.delta::<f64>(0.25, 0.5, 0.1), 0.25);
+        assert_approx_eq!(UpdateRule::Adaptive.delta::<f64>(0.25, 0.5, 0.1), 0.025);
+        assert_approx_eq!(UpdateRule::LocalError.delta::<f64>(0.25, 0.5, 0.1), 0.125);
+
+        // the local error is clamped to 1, unlike in the paper
+        assert_approx_eq!(UpdateRule::LocalError.delta::<f64>(0.25, 1.0, 0.1), 0.25);
+        assert_approx_eq!(UpdateRule::LocalError.delta::<f64>(0.25, 1.5, 0.1), 0.25);
+        assert_approx_eq!(UpdateRule::LocalError.delta::<f64>(0.25, 200.0, 0.1), 0.25);
+    }
+
+    #[test]
+    fn test_serde() {
+        let a = VivaldiConfig::<f64>::builder()
+            .c_delta(0.5)
+            .build()
+            .expect("valid config rejected during test");
+        let s = serde_json::to_string(&a).expect("serialization failed during test");
+        let b: VivaldiConfig =
+            serde_json::from_str(&s).expect("deserialization failed during test");
+        assert_eq!(a, b);
+
+        // missing fields take their default values
+        let c: VivaldiConfig =
+            serde_json::from_str("{\"c_error\":0.5}").expect("deserialization failed during test");
+        assert_approx_eq!(c.c_error(), 0.5);
+        assert_approx_eq!(c.c_delta(), 0.25);
+
+        // update rules use snake case names
+        let rule: VivaldiConfig = serde_json::from_str("{\"update_rule\":\"local_error\"}")
+            .expect("deserialization failed during test");
+        assert_eq!(rule.update_rule(), UpdateRule::LocalError);
+
+        // out of range values are rejected
+        assert!(serde_json::from_str::<VivaldiConfig>("{\"c_delta\":2.0}").is_err());
+    }
+}
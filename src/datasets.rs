@@ -0,0 +1,300 @@
+//! Loaders for N-to-N latency datasets, such as the
+//! [NetLatency-Data](https://github.com/uofa-rzhu3/NetLatency-Data) PlanetLab and Seattle
+//! matrices, for simulations and tests.
+//!
+//! Both formats are plain text, one row of whitespace separated RTTs per line, where the value on
+//! line `i`, column `j` is the RTT measured from node `i` to node `j`. They only differ in units:
+//! PlanetLab RTTs are in milliseconds, Seattle RTTs are in seconds.
+//!
+//! This module is only available with the `datasets` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use vivaldi_nc::datasets::{DatasetFormat, LatencyMatrix};
+//!
+//! let matrix = LatencyMatrix::parse("0 0.03\n0.03 0\n", DatasetFormat::Seattle).unwrap();
+//! assert_eq!(matrix.dimensions(), (2, 2));
+//! assert_eq!(matrix.get(0, 1).unwrap().as_millis(), 30);
+//! ```
+
+use core::time::Duration;
+use std::{fs, io::Read, path::Path};
+
+use crate::error::DatasetError;
+
+//
+// **** Enums ****
+//
+
+/// The format of a dataset file, which determines the unit of its RTTs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DatasetFormat {
+    /// RTTs in milliseconds, as in the PlanetLab matrices.
+    PlanetLab,
+    /// RTTs in seconds, as in the Seattle matrices.
+    Seattle,
+}
+
+//
+// **** Structs ****
+//
+
+/// A square matrix of measured RTTs between the nodes of a network.
+///
+/// Zero entries, like the diagonal, are missing measurements and read back as `None`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LatencyMatrix {
+    size: usize,
+    rtts: Vec<Option<Duration>>,
+}
+
+//
+// **** Implementations ****
+//
+
+impl DatasetFormat {
+    /// The number of seconds per unit of the values in the file.
+    const fn seconds_per_unit(self) -> f64 {
+        match self {
+            Self::PlanetLab => 0.001,
+            Self::Seattle => 1.0,
+        }
+    }
+}
+
+impl LatencyMatrix {
+    /// Parses a matrix of any size from the contents of a dataset file.
+    ///
+    /// Blank lines are ignored.
+    ///
+    /// # Errors
+    ///
+    /// - [`DatasetError::Empty`] if there are no rows
+    /// - [`DatasetError::Parse`] if a value isn't a number
+    /// - [`DatasetError::InvalidValue`] if a value is negative, NaN, or too large
+    /// - [`DatasetError::RaggedRow`] if the rows don't all have the same length
+    /// - [`DatasetError::NotSquare`] if there are more or fewer rows than columns
+    pub fn parse(s: &str, format: DatasetFormat) -> Result<Self, DatasetError> {
+        let mut columns = None;
+        let mut rows = 0;
+        let mut rtts = Vec::new();
+
+        for (line, text) in (1..).zip(s.lines()) {
+            if text.trim().is_empty() {
+                continue;
+            }
+            let start = rtts.len();
+            for (column, token) in (1..).zip(text.split_whitespace()) {
+                rtts.push(parse_rtt(token, format, line, column)?);
+            }
+
+            let found = rtts.len() - start;
+            let expected = *columns.get_or_insert(found);
+            if found != expected {
+                return Err(DatasetError::RaggedRow {
+                    line,
+                    expected,
+                    found,
+                });
+            }
+            rows += 1;
+        }
+
+        match columns {
+            None => Err(DatasetError::Empty),
+            Some(columns) if columns != rows => Err(DatasetError::NotSquare { rows, columns }),
+            Some(size) => Ok(Self { size, rtts }),
+        }
+    }
+
+    /// Reads and parses a matrix, see [`LatencyMatrix::parse`].
+    ///
+    /// # Errors
+    ///
+    /// - [`DatasetError::Io`] if reading fails, or the contents aren't UTF-8
+    /// - any error returned by [`LatencyMatrix::parse`]
+    pub fn from_reader<R>(mut reader: R, format: DatasetFormat) -> Result<Self, DatasetError>
+    where
+        R: Read,
+    {
+        let mut s = String::new();
+        reader.read_to_string(&mut s)?;
+        Self::parse(&s, format)
+    }
+
+    /// Loads a matrix from a file, see [`LatencyMatrix::parse`].
+    ///
+    /// # Errors
+    ///
+    /// - [`DatasetError::Io`] if the file can't be read, or its contents aren't UTF-8
+    /// - any error returned by [`LatencyMatrix::parse`]
+    pub fn load<P>(path: P, format: DatasetFormat) -> Result<Self, DatasetError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::parse(&fs::read_to_string(path)?, format)
+    }
+
+    /// The number of nodes in the matrix.
+    #[must_use]
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The dimensions of the matrix as `(rows, columns)`, which are always equal.
+    #[must_use]
+    pub const fn dimensions(&self) -> (usize, usize) {
+        (self.size, self.size)
+    }
+
+    /// The RTT measured from node `i` to node `j`, or `None` if it's missing or either index is
+    /// out of range.
+    #[must_use]
+    pub fn get(&self, i: usize, j: usize) -> Option<Duration> {
+        if i < self.size && j < self.size {
+            self.rtts[i * self.size + j]
+        } else {
+            None
+        }
+    }
+
+    /// Iterates over the rows of the matrix. Collected into a `Vec`, they can be passed straight
+    /// to [`solve_from_matrix`](crate::solver::solve_from_matrix).
+    pub fn rows(&self) -> impl Iterator<Item = &[Option<Duration>]> + '_ {
+        self.rtts.chunks_exact(self.size)
+    }
+}
+
+//
+// **** Functions ****
+//
+
+/// Parses the value at `line` and `column`, which is `None` if it's zero.
+fn parse_rtt(
+    token: &str,
+    format: DatasetFormat,
+    line: usize,
+    column: usize,
+) -> Result<Option<Duration>, DatasetError> {
+    let value: f64 = token
+        .parse()
+        .map_err(|_| DatasetError::Parse { line, column })?;
+    let secs = value * format.seconds_per_unit();
+    if !(secs >= 0.0 && secs < Duration::MAX.as_secs_f64()) {
+        Err(DatasetError::InvalidValue { line, column })
+    } else if secs == 0.0 {
+        Ok(None)
+    } else {
+        Ok(Some(Duration::from_secs_f64(secs)))
+    }
+}
+
+//
+// **** Tests ****
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Path of a file in the bundled copy of NetLatency-Data.
+    fn data_file(name: &str) -> String {
+        format!(
+            "{}/examples/NetLatency-Data/{name}",
+            env!("CARGO_MANIFEST_DIR")
+        )
+    }
+
+    #[test]
+    fn test_parse() {
+        let s = "0\t12.5\t40\n12.5\t0\t30\n\n40\t30\t0\n";
+        let matrix =
+            LatencyMatrix::parse(s, DatasetFormat::PlanetLab).expect("valid matrix rejected");
+        assert_eq!(matrix.size(), 3);
+        assert_eq!(matrix.dimensions(), (3, 3));
+        assert_eq!(matrix.get(0, 0), None);
+        assert_eq!(matrix.get(0, 1), Some(Duration::from_micros(12_500)));
+        assert_eq!(matrix.get(2, 1), Some(Duration::from_millis(30)));
+        assert_eq!(matrix.get(3, 0), None);
+        assert_eq!(matrix.rows().count(), 3);
+        assert_eq!(
+            matrix.rows().nth(1).expect("missing row during test"),
+            &[
+                Some(Duration::from_micros(12_500)),
+                None,
+                Some(Duration::from_millis(30))
+            ]
+        );
+
+        // the same values in seconds
+        let s = "0 0.0125 0.04\n0.0125 0 0.03\n0.04 0.03 0";
+        let seattle =
+            LatencyMatrix::parse(s, DatasetFormat::Seattle).expect("valid matrix rejected");
+        assert_eq!(seattle, matrix);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let parse = |s| LatencyMatrix::parse(s, DatasetFormat::PlanetLab);
+        assert!(matches!(parse(""), Err(DatasetError::Empty)));
+        assert!(matches!(parse("\n  \n"), Err(DatasetError::Empty)));
+        assert!(matches!(
+            parse("0 1\n1 x"),
+            Err(DatasetError::Parse { line: 2, column: 2 })
+        ));
+        assert!(matches!(
+            parse("0 -1\n1 0"),
+            Err(DatasetError::InvalidValue { line: 1, column: 2 })
+        ));
+        assert!(matches!(
+            parse("0 NaN\n1 0"),
+            Err(DatasetError::InvalidValue { line: 1, column: 2 })
+        ));
+        assert!(matches!(
+            parse("0 1\n\n1"),
+            Err(DatasetError::RaggedRow {
+                line: 3,
+                expected: 2,
+                found: 1
+            })
+        ));
+        assert!(matches!(
+            parse("0 1 2\n1 0 2"),
+            Err(DatasetError::NotSquare {
+                rows: 2,
+                columns: 3
+            })
+        ));
+        assert!(matches!(
+            LatencyMatrix::load(data_file("missing"), DatasetFormat::PlanetLab),
+            Err(DatasetError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let matrix = LatencyMatrix::from_reader(&b"0 5\n5 0\n"[..], DatasetFormat::PlanetLab)
+            .expect("valid matrix rejected");
+        assert_eq!(matrix.get(1, 0), Some(Duration::from_millis(5)));
+    }
+
+    #[test]
+    fn test_load_planetlab() {
+        let matrix = LatencyMatrix::load(
+            data_file("PlanetLab/PlanetLabData_1"),
+            DatasetFormat::PlanetLab,
+        )
+        .expect("unable to load PlanetLab data during test");
+        assert_eq!(matrix.dimensions(), (490, 490));
+        assert_eq!(matrix.get(0, 1), Some(Duration::from_micros(175)));
+    }
+
+    #[test]
+    fn test_load_seattle() {
+        let matrix =
+            LatencyMatrix::load(data_file("Seattle/SeattleData_1"), DatasetFormat::Seattle)
+                .expect("unable to load Seattle data during test");
+        assert_eq!(matrix.dimensions(), (99, 99));
+        assert_eq!(matrix.get(0, 1), Some(Duration::from_millis(170)));
+    }
+}
@@ -0,0 +1,267 @@
+//! Error types returned by the fallible parts of the interface.
+
+use core::fmt;
+
+//
+// **** Enums ****
+//
+
+/// Error returned when building a [`VivaldiConfig`](crate::VivaldiConfig) with a tuning parameter
+/// outside of its allowed range.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The named parameter was NaN, infinite, or outside of the `expected` range.
+    OutOfRange {
+        /// name of the offending parameter
+        parameter: &'static str,
+        /// human readable description of the allowed range
+        expected: &'static str,
+    },
+}
+
+/// Error returned when a sample is rejected by
+/// [`NetworkCoordinate::try_update`](crate::NetworkCoordinate::try_update).
+///
+/// The local coordinate is never modified when one of these is returned.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UpdateError {
+    /// The measured RTT was zero, or too small to compute a relative error from.
+    RttTooSmall,
+    /// The remote coordinate has a NaN, infinite, or negative component.
+    InvalidRemoteCoordinate,
+    /// The remote error estimate was zero, negative, or not finite.
+    InvalidRemoteError,
+    /// Applying the sample would have produced a NaN or infinite value.
+    NumericOverflow,
+    /// A batch update had no samples.
+    EmptyBatch,
+}
+
+/// Error returned when [`solve_from_matrix`](crate::solver::solve_from_matrix) can't use its
+/// input.
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SolverError {
+    /// The RTT matrix isn't square, i.e. a row doesn't have one entry per node.
+    NotSquare,
+}
+
+/// Error returned when a [`LatencyMatrix`](crate::datasets::LatencyMatrix) can't be loaded.
+///
+/// Line and column numbers start at 1, columns count whitespace separated values.
+#[cfg(feature = "datasets")]
+#[derive(Debug)]
+pub enum DatasetError {
+    /// The file couldn't be opened or read.
+    Io(std::io::Error),
+    /// There were no rows.
+    Empty,
+    /// A value wasn't a number.
+    Parse {
+        /// line of the offending value
+        line: usize,
+        /// column of the offending value
+        column: usize,
+    },
+    /// A value was negative, NaN, or too large to be an RTT.
+    InvalidValue {
+        /// line of the offending value
+        line: usize,
+        /// column of the offending value
+        column: usize,
+    },
+    /// A row had a different number of values than the first row.
+    RaggedRow {
+        /// line of the offending row
+        line: usize,
+        /// number of values in the first row
+        expected: usize,
+        /// number of values in this row
+        found: usize,
+    },
+    /// The number of rows differs from the number of columns.
+    NotSquare {
+        /// number of rows
+        rows: usize,
+        /// number of columns
+        columns: usize,
+    },
+}
+
+/// Error returned when a coordinate fails validation, e.g. while deserializing a coordinate
+/// received from a remote node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CoordinateError {
+    /// A position component was NaN or infinite.
+    NonFinitePosition,
+    /// The height was negative, NaN, or infinite.
+    InvalidHeight,
+    /// The error estimate was zero, negative, or not finite.
+    InvalidError,
+    /// The adjustment term was NaN or infinite.
+    InvalidAdjustment,
+    /// The curvature of a hyperbolic space was zero, negative, or not finite.
+    InvalidCurvature,
+    /// The radius of a sphere was zero, negative, or not finite.
+    InvalidRadius,
+    /// The position of a spherical coordinate wasn't on its sphere.
+    OffSphere,
+    /// The coordinate was serialized with a different
+    /// [`CoordinateModel`](crate::CoordinateModel) than expected.
+    ModelMismatch,
+    /// A point wasn't valid in its [`LatencySpace`](crate::LatencySpace), e.g. it had a NaN
+    /// component.
+    InvalidPoint,
+}
+
+//
+// **** Trait Implementations ****
+//
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange {
+                parameter,
+                expected,
+            } => write!(f, "`{parameter}` must be {expected}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConfigError {}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::RttTooSmall => "measured RTT is too small",
+            Self::InvalidRemoteCoordinate => "remote coordinate is not valid",
+            Self::InvalidRemoteError => "remote error estimate is not positive and finite",
+            Self::NumericOverflow => "update produced a non-finite value",
+            Self::EmptyBatch => "batch has no samples",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UpdateError {}
+
+#[cfg(feature = "std")]
+impl fmt::Display for SolverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NotSquare => "RTT matrix is not square",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SolverError {}
+
+#[cfg(feature = "datasets")]
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "unable to read dataset: {e}"),
+            Self::Empty => f.write_str("dataset has no rows"),
+            Self::Parse { line, column } => {
+                write!(f, "value at line {line}, column {column} is not a number")
+            }
+            Self::InvalidValue { line, column } => {
+                write!(
+                    f,
+                    "value at line {line}, column {column} is not a valid RTT"
+                )
+            }
+            Self::RaggedRow {
+                line,
+                expected,
+                found,
+            } => write!(f, "line {line} has {found} values, expected {expected}"),
+            Self::NotSquare { rows, columns } => {
+                write!(f, "matrix has {rows} rows and {columns} columns")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "datasets")]
+impl std::error::Error for DatasetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "datasets")]
+impl From<std::io::Error> for DatasetError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl fmt::Display for CoordinateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NonFinitePosition => "position components must be finite",
+            Self::InvalidHeight => "height must be finite and not negative",
+            Self::InvalidError => "error must be finite and greater than zero",
+            Self::InvalidAdjustment => "adjustment must be finite",
+            Self::InvalidCurvature => "curvature must be finite and greater than zero",
+            Self::InvalidRadius => "radius must be finite and greater than zero",
+            Self::OffSphere => "position must be on the surface of the sphere",
+            Self::ModelMismatch => "coordinate uses a different model",
+            Self::InvalidPoint => "point must be valid in its latency space",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CoordinateError {}
+
+//
+// **** Tests ****
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_error_display() {
+        let e = ConfigError::OutOfRange {
+            parameter: "c_error",
+            expected: "in (0, 1]",
+        };
+        assert_eq!(e.to_string(), "`c_error` must be in (0, 1]");
+    }
+
+    #[test]
+    fn test_update_error_display() {
+        assert_eq!(
+            UpdateError::RttTooSmall.to_string(),
+            "measured RTT is too small"
+        );
+    }
+
+    #[cfg(feature = "datasets")]
+    #[test]
+    fn test_dataset_error_display() {
+        let e = DatasetError::RaggedRow {
+            line: 3,
+            expected: 99,
+            found: 98,
+        };
+        assert_eq!(e.to_string(), "line 3 has 98 values, expected 99");
+    }
+
+    #[test]
+    fn test_coordinate_error_display() {
+        assert_eq!(
+            CoordinateError::InvalidHeight.to_string(),
+            "height must be finite and not negative"
+        );
+    }
+}
@@ -0,0 +1,268 @@
+//! A per-peer moving percentile filter for raw RTT samples.
+//!
+//! Raw RTT measurements are noisy and heavy-tailed. A single outlier (e.g. a sample delayed by a
+//! garbage collection pause or a full queue) can push a [`NetworkCoordinate`] far away from where
+//! it belongs. Ledlie, Gardner, and Seltzer ("Network Coordinates in the Wild", NSDI 2007) found
+//! that a moving percentile (MP) filter, which keeps a small window of recent samples for each
+//! peer and uses a low percentile of them, removes most of that noise while still tracking real
+//! latency changes.
+//!
+//! # Example
+//!
+//! ```
+//! use core::time::Duration;
+//! use vivaldi_nc::{LatencyFilter, NetworkCoordinate};
+//!
+//! let mut filter = LatencyFilter::default();
+//! let mut local: NetworkCoordinate<2> = NetworkCoordinate::new();
+//! let remote: NetworkCoordinate<2> = NetworkCoordinate::new();
+//!
+//! // one of these samples is an outlier, which the filter ignores
+//! for ms in [100, 102, 950, 101] {
+//!     filter.update(&mut local, "remote", &remote, Duration::from_millis(ms));
+//! }
+//! assert_eq!(filter.filtered(&"remote"), Some(Duration::from_millis(101)));
+//! ```
+
+use core::hash::Hash;
+use core::time::Duration;
+use std::collections::{HashMap, VecDeque};
+
+use crate::config::VivaldiConfig;
+use crate::error::ConfigError;
+use crate::latency_space::LatencySpace;
+use crate::network_coordinate::NetworkCoordinate;
+use crate::real::Real;
+
+//
+// **** Constants ****
+//
+
+// window size and percentile recommended by Ledlie et al.
+const DEFAULT_WINDOW: usize = 4;
+const DEFAULT_PERCENTILE: f64 = 0.25;
+
+//
+// **** Structs ****
+//
+
+/// A moving percentile filter which tracks a window of recent RTT samples for each peer.
+///
+/// ## Generic Parameters
+///
+/// - `K`: the type used to identify peers, e.g. a node ID or a socket address
+#[derive(Clone, Debug)]
+pub struct LatencyFilter<K> {
+    window: usize,
+    percentile: f64,
+    samples: HashMap<K, VecDeque<Duration>>,
+}
+
+//
+// **** Implementations ****
+//
+
+impl<K> LatencyFilter<K>
+where
+    K: Eq + Hash,
+{
+    /// Creates a new, empty filter.
+    ///
+    /// # Parameters
+    ///
+    /// - `window`: how many recent samples to keep for each peer, must be greater than zero
+    /// - `percentile`: which percentile of the window to output, must be in `[0, 1]`
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfigError`] if either parameter is out of range.
+    pub fn new(window: usize, percentile: f64) -> Result<Self, ConfigError> {
+        if window == 0 {
+            return Err(ConfigError::OutOfRange {
+                parameter: "window",
+                expected: "greater than zero",
+            });
+        }
+        if !(0.0..=1.0).contains(&percentile) {
+            return Err(ConfigError::OutOfRange {
+                parameter: "percentile",
+                expected: "in [0, 1]",
+            });
+        }
+        Ok(Self {
+            window,
+            percentile,
+            samples: HashMap::new(),
+        })
+    }
+
+    /// Records a new RTT sample for `peer` and returns the filtered RTT.
+    pub fn push(&mut self, peer: K, rtt: Duration) -> Duration {
+        let window = self.samples.entry(peer).or_default();
+        if window.len() == self.window {
+            window.pop_front();
+        }
+        window.push_back(rtt);
+        percentile(window, self.percentile)
+    }
+
+    /// Returns the filtered RTT for `peer`, or `None` if it has no samples.
+    #[must_use]
+    pub fn filtered(&self, peer: &K) -> Option<Duration> {
+        self.samples
+            .get(peer)
+            .map(|window| percentile(window, self.percentile))
+    }
+
+    /// Filters `rtt` and then updates `local` with the filtered RTT to `remote`.
+    ///
+    /// # Returns
+    ///
+    /// - the filtered RTT which was used for the update
+    pub fn update<const N: usize, T: Real, S: LatencySpace<T>>(
+        &mut self,
+        local: &mut NetworkCoordinate<N, T, S>,
+        peer: K,
+        remote: &NetworkCoordinate<N, T, S>,
+        rtt: Duration,
+    ) -> Duration {
+        self.update_with_config(local, peer, remote, rtt, &VivaldiConfig::default())
+    }
+
+    /// Same as [`LatencyFilter::update`], but uses the tuning parameters from `config` instead of
+    /// the defaults.
+    pub fn update_with_config<const N: usize, T: Real, S: LatencySpace<T>>(
+        &mut self,
+        local: &mut NetworkCoordinate<N, T, S>,
+        peer: K,
+        remote: &NetworkCoordinate<N, T, S>,
+        rtt: Duration,
+        config: &VivaldiConfig<T>,
+    ) -> Duration {
+        let filtered = self.push(peer, rtt);
+        local.update_with_config(remote, filtered, config);
+        filtered
+    }
+
+    /// Forgets all samples for `peer`, e.g. after it leaves the network.
+    pub fn remove(&mut self, peer: &K) {
+        self.samples.remove(peer);
+    }
+
+    /// Forgets all samples for all peers.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// The number of peers with samples in the filter.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Whether the filter has no samples at all.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+//
+// **** Trait Implementations ****
+//
+
+impl<K> Default for LatencyFilter<K>
+where
+    K: Eq + Hash,
+{
+    /// A default filter uses the window of 4 samples and 25th percentile from Ledlie et al.
+    fn default() -> Self {
+        Self {
+            window: DEFAULT_WINDOW,
+            percentile: DEFAULT_PERCENTILE,
+            samples: HashMap::new(),
+        }
+    }
+}
+
+//
+// **** Functions ****
+//
+
+/// Nearest rank percentile of a non-empty window of samples.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn percentile(window: &VecDeque<Duration>, p: f64) -> Duration {
+    let mut sorted: Vec<Duration> = window.iter().copied().collect();
+    sorted.sort_unstable();
+    let rank = (p * (sorted.len() - 1) as f64).round() as usize;
+    sorted[rank.min(sorted.len() - 1)]
+}
+
+//
+// **** Tests ****
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_invalid() {
+        assert!(LatencyFilter::<u32>::new(0, 0.5).is_err());
+        assert!(LatencyFilter::<u32>::new(4, -0.1).is_err());
+        assert!(LatencyFilter::<u32>::new(4, 1.1).is_err());
+        assert!(LatencyFilter::<u32>::new(4, f64::NAN).is_err());
+        assert!(LatencyFilter::<u32>::new(1, 1.0).is_ok());
+    }
+
+    #[test]
+    fn test_percentile() {
+        let mut f = LatencyFilter::new(5, 0.5).expect("valid filter rejected during test");
+        let ms = Duration::from_millis;
+        assert_eq!(f.push(1, ms(10)), ms(10));
+        assert_eq!(f.push(1, ms(30)), ms(30));
+        assert_eq!(f.push(1, ms(20)), ms(20));
+        assert_eq!(f.push(1, ms(50)), ms(30));
+        assert_eq!(f.push(1, ms(40)), ms(30));
+
+        // the window slides, so the oldest sample (10ms) is dropped
+        assert_eq!(f.push(1, ms(60)), ms(40));
+        assert_eq!(f.filtered(&1), Some(ms(40)));
+    }
+
+    #[test]
+    fn test_peers() {
+        let mut f = LatencyFilter::default();
+        let ms = Duration::from_millis;
+        assert!(f.is_empty());
+        f.push("a", ms(10));
+        f.push("b", ms(500));
+        f.push("a", ms(900));
+        assert_eq!(f.len(), 2);
+        assert_eq!(f.filtered(&"a"), Some(ms(10)));
+        assert_eq!(f.filtered(&"b"), Some(ms(500)));
+        assert_eq!(f.filtered(&"c"), None);
+
+        f.remove(&"a");
+        assert_eq!(f.filtered(&"a"), None);
+        f.clear();
+        assert!(f.is_empty());
+    }
+
+    #[test]
+    fn test_update() {
+        let mut f = LatencyFilter::default();
+        let mut local = NetworkCoordinate::<2>::new();
+        let remote = NetworkCoordinate::<2>::new();
+        let before = local.error();
+        for _ in 0..4 {
+            f.update(&mut local, 7_u8, &remote, Duration::from_millis(100));
+        }
+        let rtt = f.update(&mut local, 7_u8, &remote, Duration::from_secs(10));
+        assert_eq!(rtt, Duration::from_millis(100));
+        assert!(local.error() < before);
+    }
+}
@@ -41,32 +41,21 @@
 //!
 //! > Each node has a positive height element in its coordinates, so that
 //! > its height can always be scaled up or down.
+//!
+//! Note that intermediate results (like the force pulling a node toward a closer peer) can have a
+//! negative height, so the arithmetic operators only reject non-finite results. Coordinates
+//! themselves are kept valid by clamping their height with [`HeightVector::with_min_height`].
 
 use core::ops::{Add, Mul, Sub};
 
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::error::CoordinateError;
+use crate::latency_space::CoordinateModel;
+use crate::real::Real;
 use crate::vector::Vector;
 
-//
-// **** Features ****
-//
-
-cfg_if::cfg_if! {
-    if #[cfg(feature = "f32")] {
-        /// `FloatType` is a type alias for either `f32` or `f64` depending on cargo features
-        type FloatType = f32;
-    } else {
-        /// `FloatType` is a type alias for either `f32` or `f64` depending on cargo features
-        type FloatType = f64;
-    }
-}
-
-//
-// **** Constants ****
-//
-
 //
 // **** Structs ****
 //
@@ -76,48 +65,110 @@ cfg_if::cfg_if! {
 /// ## Generic Parameters
 ///
 /// - `N`: the dimensionality of the vector portion (i.e. non-height) of the Vivaldi height vector
+/// - `T`: the floating point type to compute with, `f64` by default
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
-pub struct HeightVector<const N: usize> {
+#[serde(
+    try_from = "HeightVectorRepr<N, T>",
+    bound(serialize = "T: Real", deserialize = "T: Real")
+)]
+pub struct HeightVector<const N: usize, T = f64>
+where
+    T: Real,
+{
     /// `position` is the Euclidean coordinate part of the `HeightVector`, representing a position
     /// in the network's latency space
     #[serde(flatten)]
-    position: Vector<FloatType, N>,
+    position: Vector<T, N>,
 
     /// `height` is a representation of a node's stemp time, or its latency to get into the network
     /// core (or backbone). It's a way to account for triangle inequality violations.
     /// in the network's latency space
-    height: FloatType,
+    height: T,
+}
+
+/// Unvalidated wire representation of a [`HeightVector`], only used for deserialization.
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "T: Real"))]
+struct HeightVectorRepr<const N: usize, T>
+where
+    T: Real,
+{
+    #[serde(default)]
+    model: CoordinateModel,
+    #[serde(flatten)]
+    position: Vector<T, N>,
+    // optional here, so a message using another model is rejected as such instead of as a
+    // message without a height
+    #[serde(default)]
+    height: Option<T>,
 }
 
 //
 // **** Implementations ****
 //
 
-impl<const N: usize> HeightVector<N> {
-    /// A new height vector is a random unit vector
-    pub(crate) fn random() -> Self {
-        let mut rng = thread_rng();
-        let mut vec = [0.0; N];
+impl<const N: usize, T> HeightVector<N, T>
+where
+    T: Real,
+{
+    /// Creates a height vector from its Euclidean `position` and its `height`.
+    ///
+    /// # Errors
+    ///
+    /// - [`CoordinateError::NonFinitePosition`] if a component of `position` is NaN or infinite
+    /// - [`CoordinateError::InvalidHeight`] if `height` is negative, NaN or infinite
+    pub fn new(position: [T; N], height: T) -> Result<Self, CoordinateError> {
+        let position = Vector::from(position);
+        if position.is_invalid() {
+            return Err(CoordinateError::NonFinitePosition);
+        }
+        if !(height.is_finite() && height >= T::zero()) {
+            return Err(CoordinateError::InvalidHeight);
+        }
+        Ok(Self { position, height })
+    }
+
+    /// The Euclidean part of the height vector.
+    #[must_use]
+    pub const fn position(&self) -> [T; N] {
+        self.position.to_array()
+    }
+
+    /// The height, i.e. the estimated latency from the node to the network core.
+    #[must_use]
+    pub const fn height(&self) -> T {
+        self.height
+    }
+
+    /// A new height vector is a random unit vector, drawn from `rng`
+    pub(crate) fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let mut vec = [T::zero(); N];
         for i in vec.iter_mut().take(N) {
-            *i = rng.gen::<FloatType>() - 0.5;
+            *i = T::sample(rng) - T::from_f64(0.5);
         }
-        let height = rng.gen::<FloatType>().abs();
+        let height = T::sample(rng).abs();
         Self {
-            position: Vector::<FloatType, N>::from(vec),
+            position: Vector::<T, N>::from(vec),
             height,
         }
-        .normalized()
+        .normalized(rng)
     }
 
     /// The magnitude of a Vivaldi height vector is defined as the magnitude of the vector plus the
     /// height value.
-    pub(crate) fn len(&self) -> FloatType {
+    pub(crate) fn len(&self) -> T {
         self.position.len() + self.height
     }
 
+    /// The magnitude of the change from `previous` to `self`, i.e. the distance between the
+    /// positions plus the change in height.
+    pub(crate) fn displacement(&self, previous: &Self) -> T {
+        (self.position - previous.position).len() + (self.height - previous.height).abs()
+    }
+
     /// A normalized Vivaldi height vector is just like a normalized vector: the vector scaled
     /// by the inveerse of its length.
-    pub(crate) fn normalized(&self) -> Self {
+    pub(crate) fn normalized<R: Rng + ?Sized>(&self, rng: &mut R) -> Self {
         let len = self.len();
         // scale it by the inverse of its magnitude like any normal vector
         let ret = Self {
@@ -128,7 +179,34 @@ impl<const N: usize> HeightVector<N> {
             ret
         } else {
             // if we have a bad vector, generate a new random vector
-            Self::random()
+            Self::random(rng)
+        }
+    }
+
+    /// Clamps the height to be at least `min_height`.
+    ///
+    /// Moving a node toward a peer shrinks its height along with its position. The paper keeps
+    /// heights positive so they can always be scaled up or down again, which is what this is for.
+    pub(crate) fn with_min_height(self, min_height: T) -> Self {
+        Self {
+            position: self.position,
+            height: self.height.max(min_height),
+        }
+    }
+
+    /// Pulls the position toward the origin by `(‖position‖ / rho)²`, without overshooting it.
+    ///
+    /// Only the position is affected. Drift is a translation of the whole coordinate system, and
+    /// the height models a node's access link rather than its place in the core.
+    pub(crate) fn with_gravity(self, rho: T) -> Self {
+        let dist = self.position.len();
+        if dist <= T::zero() {
+            return self;
+        }
+        let force = (dist / rho).powi(2);
+        Self {
+            position: self.position * (T::one() - force / dist).max(T::zero()),
+            height: self.height,
         }
     }
 
@@ -148,7 +226,7 @@ impl<const N: usize> HeightVector<N> {
         self.position.is_invalid()
             || self.height.is_nan()
             || self.height.is_infinite()
-            || self.height < 0.0
+            || self.height < T::zero()
     }
 }
 
@@ -156,7 +234,10 @@ impl<const N: usize> HeightVector<N> {
 // **** Trait Implementations ****
 //
 
-impl<const N: usize> Default for HeightVector<N> {
+impl<const N: usize, T> Default for HeightVector<N, T>
+where
+    T: Real,
+{
     /// Default value for a Vivaldi height vector is just the defaults of its children types.
     fn default() -> Self {
         Self {
@@ -166,69 +247,84 @@ impl<const N: usize> Default for HeightVector<N> {
     }
 }
 
-impl<const N: usize> From<([FloatType; N], FloatType)> for HeightVector<N> {
-    /// Convert from a `([FloatType; N], FloatType)` (vector, height) to a Vivaldi height vector type.
-    fn from(value: ([FloatType; N], FloatType)) -> Self {
-        let ret = Self {
-            position: Vector::<FloatType, N>::from(value.0),
-            height: value.1,
-        };
-        if ret.is_valid() {
-            ret
-        } else {
-            Self::random()
+impl<const N: usize, T> TryFrom<([T; N], T)> for HeightVector<N, T>
+where
+    T: Real,
+{
+    type Error = CoordinateError;
+
+    /// Convert from a `([T; N], T)` (vector, height) to a Vivaldi height vector type, validated
+    /// the same way as [`HeightVector::new`].
+    fn try_from(value: ([T; N], T)) -> Result<Self, Self::Error> {
+        Self::new(value.0, value.1)
+    }
+}
+
+impl<const N: usize, T> TryFrom<HeightVectorRepr<N, T>> for HeightVector<N, T>
+where
+    T: Real,
+{
+    type Error = CoordinateError;
+
+    /// Validate a deserialized height vector, because a remote node sending garbage should be
+    /// rejected rather than papered over.
+    fn try_from(value: HeightVectorRepr<N, T>) -> Result<Self, Self::Error> {
+        if value.model != CoordinateModel::HeightVector {
+            return Err(CoordinateError::ModelMismatch);
+        }
+        match value.height {
+            Some(height) if height.is_finite() && height >= T::zero() => Ok(Self {
+                position: value.position,
+                height,
+            }),
+            _ => Err(CoordinateError::InvalidHeight),
         }
     }
 }
 
-impl<const N: usize> Add for HeightVector<N> {
+impl<const N: usize, T> Add for HeightVector<N, T>
+where
+    T: Real,
+{
     type Output = Self;
 
     /// Add two Vivaldi height vectors.
     fn add(self, rhs: Self) -> Self::Output {
-        let ret = Self {
+        Self {
             position: self.position + rhs.position,
             height: self.height + rhs.height,
-        };
-        if ret.is_valid() {
-            ret
-        } else {
-            Self::random()
         }
     }
 }
 
-impl<const N: usize> Sub for HeightVector<N> {
+impl<const N: usize, T> Sub for HeightVector<N, T>
+where
+    T: Real,
+{
     type Output = Self;
 
     /// Subtract two Vivaldi height vectors. Note that this is the difference in the vectors
     /// and the summation of the heights, as defined by Vivaldi's author.
     fn sub(self, rhs: Self) -> Self::Output {
-        let ret = Self {
+        Self {
             position: self.position - rhs.position,
             height: self.height + rhs.height,
-        };
-        if ret.is_valid() {
-            ret
-        } else {
-            Self::random()
         }
     }
 }
 
-impl<const N: usize> Mul<FloatType> for HeightVector<N> {
+impl<const N: usize, T> Mul<T> for HeightVector<N, T>
+where
+    T: Real,
+{
     type Output = Self;
 
-    /// Multiply a Vivaldi height vector by a scalar. Works the same as normal vector scaling.
-    fn mul(self, rhs: FloatType) -> Self::Output {
-        let ret = Self {
+    /// Multiply a Vivaldi height vector by a scalar. Works the same as normal vector scaling, so a
+    /// negative scalar also gives a negative height.
+    fn mul(self, rhs: T) -> Self::Output {
+        Self {
             position: self.position * rhs,
             height: self.height * rhs,
-        };
-        if ret.is_valid() {
-            ret
-        } else {
-            Self::random()
         }
     }
 }
@@ -243,19 +339,25 @@ mod tests {
     use assert_approx_eq::assert_approx_eq;
     use proptest::prelude::*;
 
-    fn approx_eq(a: FloatType, b: FloatType, margin: FloatType) -> bool {
+    fn hv<const N: usize>(position: [f64; N], height: f64) -> HeightVector<N> {
+        HeightVector::try_from((position, height))
+            .expect("valid height vector rejected during test")
+    }
+
+    fn approx_eq(a: f64, b: f64, margin: f64) -> bool {
         (a - b).abs() <= margin
     }
 
     proptest! {
         #[test]
-        fn proptest_len(x: FloatType, y: FloatType, h: FloatType) {
+        fn proptest_len(x: f64, y: f64, h: f64) {
             let len = x.hypot(y) + h.abs();
-            let a = HeightVector::<2>::from(([x,y],h));
+            let a = HeightVector::<2>::try_from(([x,y],h));
             if x.is_nan() || x.is_infinite() || y.is_nan() || y.is_infinite() || h.is_nan() || h.is_infinite() || h < 0.0 {
-                // we should've gottne a random univ vector here
-                prop_assert!(approx_eq(a.len(), 1.0, 0.0001));
+                // we should've gotten an error here, never an invalid vector
+                prop_assert!(a.is_err());
             } else {
+                let a = a.expect("valid height vector rejected during test");
                 // `HeightVector` we created from proptest values should be valid and have a length
                 if len.is_nan() || len.is_infinite() {
                     prop_assert!(a.len().is_nan() || a.len().is_infinite());
@@ -268,12 +370,12 @@ mod tests {
         #[test]
         #[allow(clippy::cast_lossless, clippy::cast_precision_loss)]
         fn proptest_add(x0 in -1_000_000_000..1_000_000_000i32, y0 in -1_000_000_000..1_000_000_000i32, h0 in 0..1_000_000_000i32, x1 in -1_000_000_000..1_000_000_000i32, y1 in -1_000_000_000..1_000_000_000i32, h1 in 0..1_000_000i32) {
-            // convert our integer range inputs to FloatType
-            let (fx0,fy0,fh0) = (x0 as FloatType / 1_000.0, y0 as FloatType / 1_000.0, h0 as FloatType / 1_000.0);
-            let (fx1,fy1,fh1) = (x1 as FloatType / 1_000.0, y1 as FloatType / 1_000.0, h1 as FloatType / 1_000.0);
+            // convert our integer range inputs to f64
+            let (fx0,fy0,fh0) = (x0 as f64 / 1_000.0, y0 as f64 / 1_000.0, h0 as f64 / 1_000.0);
+            let (fx1,fy1,fh1) = (x1 as f64 / 1_000.0, y1 as f64 / 1_000.0, h1 as f64 / 1_000.0);
 
-            let a = HeightVector::<2>::from(([fx0 ,fy0 ],fh0 ));
-            let b = HeightVector::<2>::from(([fx1 ,fy1 ],fh1 ));
+            let a = hv([fx0 ,fy0 ], fh0);
+            let b = hv([fx1 ,fy1 ], fh1);
             let c = a+b;
             prop_assert!(approx_eq(c.position[0], fx0 + fx1, 0.0001));
             prop_assert!(approx_eq(c.position[1], fy0 + fy1, 0.0001));
@@ -283,12 +385,12 @@ mod tests {
         #[test]
         #[allow(clippy::cast_lossless, clippy::cast_precision_loss)]
         fn proptest_sub(x0 in -1_000_000_000..1_000_000_000i32, y0 in -1_000_000_000..1_000_000_000i32, h0 in 0..1_000_000_000i32, x1 in -1_000_000_000..1_000_000_000i32, y1 in -1_000_000_000..1_000_000_000i32, h1 in 0..1_000_000i32) {
-            // convert our integer range inputs to FloatType
-            let (fx0,fy0,fh0) = (x0 as FloatType / 1_000.0, y0 as FloatType / 1_000.0, h0 as FloatType / 1_000.0);
-            let (fx1,fy1,fh1) = (x1 as FloatType / 1_000.0, y1 as FloatType / 1_000.0, h1 as FloatType / 1_000.0);
+            // convert our integer range inputs to f64
+            let (fx0,fy0,fh0) = (x0 as f64 / 1_000.0, y0 as f64 / 1_000.0, h0 as f64 / 1_000.0);
+            let (fx1,fy1,fh1) = (x1 as f64 / 1_000.0, y1 as f64 / 1_000.0, h1 as f64 / 1_000.0);
 
-            let a = HeightVector::<2>::from(([fx0 ,fy0 ],fh0 ));
-            let b = HeightVector::<2>::from(([fx1 ,fy1 ],fh1 ));
+            let a = hv([fx0 ,fy0 ], fh0);
+            let b = hv([fx1 ,fy1 ], fh1);
             let c = a-b;
             prop_assert!(approx_eq(c.position[0], fx0 - fx1, 0.0001));
             prop_assert!(approx_eq(c.position[1], fy0 - fy1, 0.0001));
@@ -298,31 +400,27 @@ mod tests {
         #[test]
         #[allow(clippy::cast_lossless, clippy::cast_precision_loss)]
         fn proptest_mul(x in -1_000_000_000..1_000_000_000i32, y in -1_000_000_000..1_000_000_000i32, h in 0..1_000_000_000i32, m in -1_000_000_000..1_000_000_000i32) {
-            // convert our integer range inputs to FloatType
-            let (fx,fy,fh,fm) = (x as FloatType / 1_000.0, y as FloatType / 1_000.0, h as FloatType / 1_000.0, m as FloatType / 1_000.0);
+            // convert our integer range inputs to f64
+            let (fx,fy,fh,fm) = (x as f64 / 1_000.0, y as f64 / 1_000.0, h as f64 / 1_000.0, m as f64 / 1_000.0);
 
-            let a = HeightVector::<2>::from(([fx, fy], fh));
+            let a = hv([fx, fy], fh);
             let b = a * fm;
-            if fm < 0.0 {
-                prop_assert!(approx_eq(b.len(), 1.0, 0.0001));
-            } else {
-                prop_assert!(approx_eq(b.position[0], fx * fm, 0.0001));
-                prop_assert!(approx_eq(b.position[1], fy * fm, 0.0001));
-                prop_assert!(approx_eq(b.height, fh * fm, 0.0001));
-            }
+            prop_assert!(approx_eq(b.position[0], fx * fm, 0.0001));
+            prop_assert!(approx_eq(b.position[1], fy * fm, 0.0001));
+            prop_assert!(approx_eq(b.height, fh * fm, 0.0001));
         }
     }
 
     #[test]
     fn test_len() {
-        let a = HeightVector::<3>::from(([1.0, 2.0, 3.0], 4.0));
+        let a = hv([1.0, 2.0, 3.0], 4.0);
         assert_approx_eq!(a.len(), 7.741_657, 0.00001);
     }
 
     #[test]
     fn test_new() {
         // new gives us a random unit length vec
-        let a = HeightVector::<3>::random();
+        let a = HeightVector::<3>::random(&mut thread_rng());
         assert_approx_eq!(a.len(), 1.0);
     }
 
@@ -335,16 +433,26 @@ mod tests {
 
     #[test]
     fn test_from() {
-        let a = HeightVector::<2>::from(([1.0, 2.0], 3.0));
+        let a = hv([1.0, 2.0], 3.0);
         assert_approx_eq!(a.position[0], 1.0);
         assert_approx_eq!(a.position[1], 2.0);
         assert_approx_eq!(a.height, 3.0);
     }
 
+    #[test]
+    fn test_new_validated() {
+        let a = HeightVector::<2>::new([1.0, 2.0], 3.0).expect("valid vector rejected during test");
+        assert_approx_eq!(a.position()[0], 1.0);
+        assert_approx_eq!(a.position()[1], 2.0);
+        assert_approx_eq!(a.height(), 3.0);
+        assert!(HeightVector::<2>::new([f64::INFINITY, 2.0], 3.0).is_err());
+        assert!(HeightVector::<2>::new([1.0, 2.0], -3.0).is_err());
+    }
+
     #[test]
     fn test_add() {
-        let a = HeightVector::<2>::from(([1.0, 2.0], 3.0));
-        let b = HeightVector::<2>::from(([3.0, 2.0], 1.0));
+        let a = hv([1.0, 2.0], 3.0);
+        let b = hv([3.0, 2.0], 1.0);
         let c = a + b;
         assert_approx_eq!(c.position[0], 4.0);
         assert_approx_eq!(c.position[1], 4.0);
@@ -353,8 +461,8 @@ mod tests {
 
     #[test]
     fn test_sub() {
-        let a = HeightVector::<2>::from(([1.0, 2.0], 3.0));
-        let b = HeightVector::<2>::from(([3.0, 2.0], 1.0));
+        let a = hv([1.0, 2.0], 3.0);
+        let b = hv([3.0, 2.0], 1.0);
         let c = a - b;
         assert_approx_eq!(c.position[0], -2.0);
         assert_approx_eq!(c.position[1], 0.0);
@@ -363,12 +471,52 @@ mod tests {
 
     #[test]
     fn test_mul_scalar() {
-        let a = HeightVector::<2>::from(([1.0, 2.0], 3.0)) * 10.0;
+        let a = hv([1.0, 2.0], 3.0) * 10.0;
         assert_approx_eq!(a.position[0], 10.0);
         assert_approx_eq!(a.position[1], 20.0);
         assert_approx_eq!(a.height, 30.0);
     }
 
+    #[test]
+    fn test_mul_negative() {
+        let a = hv([1.0, 2.0], 3.0) * -10.0;
+        assert_approx_eq!(a.position[0], -10.0);
+        assert_approx_eq!(a.position[1], -20.0);
+        assert_approx_eq!(a.height, -30.0);
+
+        // adding it moves the position along the real direction, and lets the height go negative
+        let b = hv([0.0, 0.0], 1.0) + a;
+        assert_approx_eq!(b.position[0], -10.0);
+        assert_approx_eq!(b.position[1], -20.0);
+        assert_approx_eq!(b.height, -29.0);
+        assert!(b.is_invalid());
+
+        // until it's clamped
+        let c = b.with_min_height(0.5);
+        assert_approx_eq!(c.position[0], -10.0);
+        assert_approx_eq!(c.position[1], -20.0);
+        assert_approx_eq!(c.height, 0.5);
+        assert!(c.is_valid());
+    }
+
+    #[test]
+    fn test_gravity() {
+        // 300 from the origin with rho = 150 is pulled in by (300 / 150)² = 4
+        let a = hv([300.0, 0.0], 5.0).with_gravity(150.0);
+        assert_approx_eq!(a.position[0], 296.0);
+        assert_approx_eq!(a.position[1], 0.0);
+        assert_approx_eq!(a.height, 5.0);
+
+        // strong gravity never pulls past the origin
+        let b = hv([3.0, 4.0], 5.0).with_gravity(1.0);
+        assert_approx_eq!(b.position.len(), 0.0);
+
+        // nothing to do at the origin
+        let c = hv([0.0, 0.0], 5.0).with_gravity(1.0);
+        assert!(c.is_valid());
+        assert_approx_eq!(c.len(), 5.0);
+    }
+
     #[test]
     fn test_serde() {
         // start with JSON, deserialize it
@@ -384,35 +532,83 @@ mod tests {
         assert_eq!(t.as_ref().expect("serialization failed during test"), s);
     }
 
+    #[test]
+    fn test_deserialize_invalid() {
+        let s = "{\"position\":[1.0,2.0,3.0],\"height\":-4.0}";
+        let e = serde_json::from_str::<HeightVector<3>>(s)
+            .expect_err("invalid height accepted during test");
+        assert!(e
+            .to_string()
+            .contains("height must be finite and not negative"));
+
+        // missing height is rejected
+        assert!(serde_json::from_str::<HeightVector<3>>("{\"position\":[1.0,2.0,3.0]}").is_err());
+
+        // so is another model, but the height vector model may be explicit
+        let s = "{\"model\":\"spherical\",\"position\":[1.0,2.0,3.0],\"height\":4.0}";
+        assert!(serde_json::from_str::<HeightVector<3>>(s).is_err());
+        let s = "{\"model\":\"height_vector\",\"position\":[1.0,2.0,3.0],\"height\":4.0}";
+        assert!(serde_json::from_str::<HeightVector<3>>(s).is_ok());
+    }
+
     #[test]
     fn test_sub_invalid() {
-        let valid = HeightVector::<2>::from(([1.0, 2.0], 3.0));
-        let mut invalid = HeightVector::<2>::from(([1.0, 2.0], 3.0));
-        // force invalidity here - because `::from()` actually also catches this
+        let valid = hv([1.0, 2.0], 3.0);
+
+        // make sure `try_from()` catches the invalid height
+        assert_eq!(
+            HeightVector::<2>::try_from(([1.0, 2.0], 1.0 / 0.0)).err(),
+            Some(CoordinateError::InvalidHeight)
+        );
+
+        // force invalidity here - because `::try_from()` won't build one
+        let mut invalid = valid;
         invalid.height = 1.0 / 0.0;
+
+        // the arithmetic doesn't fall back to a random vector any more, so the invalid result
+        // must be caught by validation before it's used
         let result = valid - invalid;
-        assert_approx_eq!(result.len(), 1.0);
+        assert!(result.is_invalid());
+        assert!(HeightVector::<2>::try_from((result.position(), result.height())).is_err());
     }
 
     #[test]
     fn test_add_invalid() {
-        let valid = HeightVector::<2>::from(([1.0, 2.0], 3.0));
-        let mut invalid = HeightVector::<2>::from(([1.0 / 0.0, 2.0], 3.0));
+        let valid = hv([1.0, 2.0], 3.0);
 
-        // make sure `from()` caught and replaced the invalid vec
-        assert_approx_eq!(invalid.len(), 1.0);
+        // make sure `try_from()` catches the invalid position
+        assert_eq!(
+            HeightVector::<2>::try_from(([1.0 / 0.0, 2.0], 3.0)).err(),
+            Some(CoordinateError::NonFinitePosition)
+        );
 
-        // force invalidity here - because `::from()` actually also catches this
+        // force invalidity here - because `::try_from()` won't build one
+        let mut invalid = valid;
         invalid.height = 1.0 / 0.0;
+
         let result = valid + invalid;
-        assert_approx_eq!(result.len(), 1.0);
+        assert!(result.is_invalid());
+        assert!(HeightVector::<2>::try_from((result.position(), result.height())).is_err());
     }
 
     #[test]
     fn test_zero_norm() {
-        let a = HeightVector::<2>::from(([0.0, 0.0], 0.0));
-        let b = a.normalized();
+        let a = hv([0.0, 0.0], 0.0);
+        let b = a.normalized(&mut thread_rng());
         let len = b.len();
         assert_approx_eq!(len, 1.0);
     }
+
+    #[test]
+    fn test_seeded_random() {
+        let a = HeightVector::<3>::random(&mut StdRng::seed_from_u64(1));
+        let b = HeightVector::<3>::random(&mut StdRng::seed_from_u64(1));
+        let c = HeightVector::<3>::random(&mut StdRng::seed_from_u64(2));
+        assert_eq!(
+            a.position.to_array().map(f64::to_bits),
+            b.position.to_array().map(f64::to_bits)
+        );
+        assert_eq!(a.height.to_bits(), b.height.to_bits());
+        assert_ne!(a.height.to_bits(), c.height.to_bits());
+    }
 }
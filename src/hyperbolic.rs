@@ -0,0 +1,440 @@
+//! Implementation of a point in hyperbolic space.
+//!
+//! Shavitt and Tankel ("Hyperbolic Embedding of Internet Graph for Distance Estimation and Overlay
+//! Construction", IEEE/ACM Transactions on Networking, 2008) observed that the Internet looks like a dense core with long
+//! tendrils hanging off of it, and that a hyperbolic space embeds that shape with lower distortion
+//! than a Euclidean one, mostly for nodes far away from the core. Most shortest paths run through
+//! the core, and in hyperbolic space most shortest paths bend toward the origin too.
+//!
+//! A [`HyperbolicVector`] uses the hyperboloid (or Loid) model of the space with curvature `−c`.
+//! Only the `N` spatial coordinates `x` are stored; the extra "time" coordinate is implied by the
+//! hyperboloid `x₀² − ‖x‖² = 1/c`. The distance between two points is
+//!
+//! ```text
+//! d(x, y) = acosh(c × (x₀y₀ − x·y)) / √c
+//! ```
+//!
+//! The curvature sets the scale where the space stops looking Euclidean: distances much shorter
+//! than `1/√c` milliseconds behave (almost) like they do in the plane, much longer distances grow
+//! only logarithmically with the coordinates. As `c` approaches zero the space approaches a
+//! Euclidean one.
+//!
+//! Vivaldi moves a coordinate along the geodesic (the hyperbolic equivalent of a straight line)
+//! through it and its peer, so a force of 10ms really moves it 10ms. The arithmetic operators work
+//! on the spatial coordinates and keep the curvature of the left hand side, so a coordinate never
+//! changes its curvature during an update.
+
+use core::ops::{Add, Mul, Sub};
+
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::CoordinateError;
+use crate::latency_space::CoordinateModel;
+use crate::real::Real;
+use crate::vector::Vector;
+
+//
+// **** Constants ****
+//
+
+// the space starts to curve noticeably at around 100ms, about the RTT across a continent
+const DEFAULT_CURVATURE: f64 = 1.0e-4;
+
+//
+// **** Structs ****
+//
+
+/// A point (or vector) in N-dimensional hyperbolic space with curvature `−c`.
+///
+/// Use it as the [`LatencySpace`](crate::LatencySpace) of a
+/// [`NetworkCoordinate`](crate::NetworkCoordinate) to embed the network in hyperbolic space:
+///
+/// ```
+/// # #[cfg(feature = "std")]
+/// # fn main() {
+/// use core::time::Duration;
+/// use vivaldi_nc::{HyperbolicVector, NetworkCoordinate};
+///
+/// let point = HyperbolicVector::<2>::random_with_curvature(1e-3).unwrap();
+/// let mut local: NetworkCoordinate<2, f64, HyperbolicVector<2>> = NetworkCoordinate::from_point(point).unwrap();
+/// let remote: NetworkCoordinate<2, f64, HyperbolicVector<2>> = NetworkCoordinate::new();
+///
+/// local.update(&remote, Duration::from_millis(100));
+/// println!("Estimated RTT: {}", local.estimated_rtt(&remote).as_millis());
+/// # }
+/// # #[cfg(not(feature = "std"))]
+/// # fn main() {}
+/// ```
+///
+/// Both sides of an update should use the same curvature. If they don't, the curvature of the
+/// local coordinate wins.
+///
+/// # Serialization
+///
+/// The spatial coordinates are serialized as `position`, next to the `curvature` and a `model`
+/// discriminator:
+///
+/// ```text
+/// {"model":"hyperbolic","position":[1.5,0.5],"curvature":0.0001,"error":1.0}
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "model",
+    rename = "hyperbolic",
+    try_from = "HyperbolicVectorRepr<N, T>",
+    bound(serialize = "T: Real", deserialize = "T: Real")
+)]
+pub struct HyperbolicVector<const N: usize, T = f64>
+where
+    T: Real,
+{
+    #[serde(flatten)]
+    position: Vector<T, N>,
+    curvature: T,
+}
+
+/// Unvalidated wire representation of a [`HyperbolicVector`], only used for deserialization.
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "T: Real"))]
+struct HyperbolicVectorRepr<const N: usize, T>
+where
+    T: Real,
+{
+    model: CoordinateModel,
+    #[serde(flatten)]
+    position: Vector<T, N>,
+    curvature: T,
+}
+
+//
+// **** Implementations ****
+//
+
+impl<const N: usize, T> HyperbolicVector<N, T>
+where
+    T: Real,
+{
+    /// Creates a point from its spatial coordinates and the curvature `c` of the space.
+    ///
+    /// # Errors
+    ///
+    /// - [`CoordinateError::NonFinitePosition`] if a component of `position` is NaN or infinite
+    /// - [`CoordinateError::InvalidCurvature`] if `curvature` isn't finite and greater than zero
+    pub fn new(position: [T; N], curvature: T) -> Result<Self, CoordinateError> {
+        let position = Vector::from(position);
+        if position.is_invalid() {
+            return Err(CoordinateError::NonFinitePosition);
+        }
+        if !is_valid_curvature(curvature) {
+            return Err(CoordinateError::InvalidCurvature);
+        }
+        Ok(Self {
+            position,
+            curvature,
+        })
+    }
+
+    /// Creates a random point one millisecond away from the origin, in a space with curvature `c`.
+    ///
+    /// # Errors
+    ///
+    /// - [`CoordinateError::InvalidCurvature`] if `curvature` isn't finite and greater than zero
+    #[cfg(feature = "std")]
+    pub fn random_with_curvature(curvature: T) -> Result<Self, CoordinateError> {
+        if is_valid_curvature(curvature) {
+            Ok(Self::random_unit(curvature, &mut thread_rng()))
+        } else {
+            Err(CoordinateError::InvalidCurvature)
+        }
+    }
+
+    /// The spatial coordinates of the point, as passed to [`HyperbolicVector::new`].
+    #[must_use]
+    pub const fn position(&self) -> [T; N] {
+        self.position.to_array()
+    }
+
+    /// The curvature `c` of the space this point lives in (the space has curvature `−c`).
+    #[must_use]
+    pub const fn curvature(&self) -> T {
+        self.curvature
+    }
+
+    /// A random unit vector drawn from `rng`, which keeps `curvature`.
+    pub(crate) fn random_unit<R: Rng + ?Sized>(curvature: T, rng: &mut R) -> Self {
+        let mut vec = [T::zero(); N];
+        for i in vec.iter_mut().take(N) {
+            *i = T::sample(rng) - T::from_f64(0.5);
+        }
+        let ret = Self {
+            position: Vector::from(vec),
+            curvature,
+        };
+        let len = ret.position.len();
+        if len > T::zero() {
+            Self {
+                position: ret.position / len,
+                curvature,
+            }
+        } else {
+            // all components came up as exactly 0.5, try again
+            Self::random_unit(curvature, rng)
+        }
+    }
+
+    /// The hyperbolic distance between two points, using the curvature of `self`.
+    pub(crate) fn distance(&self, rhs: &Self) -> T {
+        // work in a space with curvature −1, and scale the result back
+        let k = self.curvature.sqrt();
+        let (x, y) = (self.position * k, rhs.position * k);
+        let (x_len, y_len) = (x.len(), y.len());
+
+        // time coordinates on the hyperboloid
+        let x0 = x_len.hypot(T::one());
+        let y0 = y_len.hypot(T::one());
+
+        let cosh_d = x0.mul_add(y0, -x.dot(&y));
+        if cosh_d > T::from_f64(2.0) {
+            return cosh_d.acosh() / k;
+        }
+
+        // for nearby points, cosh(d) − 1 is rewritten as (‖x − y‖² − (x₀ − y₀)²) / 2, and acosh as
+        // a logarithm, so neither of them cancels out
+        let dt = (x_len - y_len) * (x_len + y_len) / (x0 + y0);
+        let two = T::from_f64(2.0);
+        let z = (dt.mul_add(-dt, (x - y).len().powi(2)) / two).max(T::zero());
+        (z + (z * (z + two)).sqrt()).ln_1p() / k
+    }
+
+    /// Moves `self` by `distance` along the geodesic through `self` and `rhs`, away from `rhs`
+    /// (or toward it if `distance` is negative).
+    ///
+    /// The spatial coordinates overstate distances far away from the origin, so moving a point
+    /// along `self − rhs` like in Euclidean space would barely change its hyperbolic distance.
+    /// Following the geodesic moves it by exactly `distance`:
+    ///
+    /// ```text
+    /// x' = cosh(√c × distance) × x + sinh(√c × distance) × (cosh(√c × d) × x − y) / sinh(√c × d)
+    /// ```
+    pub(crate) fn moved_away<R: Rng + ?Sized>(&self, rhs: &Self, distance: T, rng: &mut R) -> Self {
+        let k = self.curvature.sqrt();
+        let d = k * self.distance(rhs);
+        if !d.sinh().is_finite() {
+            // the points are too far apart to tell which way to go
+            return *self;
+        }
+        if d <= T::zero() {
+            // the points coincide, so any direction will do
+            let other = *self + Self::random_unit(self.curvature, rng);
+            return if other.position == self.position {
+                *self
+            } else {
+                self.moved_away(&other, distance, rng)
+            };
+        }
+        let t = k * distance;
+        Self {
+            position: self.position * t.cosh()
+                + (self.position * d.cosh() - rhs.position) * (t.sinh() / d.sinh()),
+            curvature: self.curvature,
+        }
+    }
+
+    /// This vector scaled to unit (spatial) magnitude, or a random unit vector if that's not
+    /// possible.
+    pub(crate) fn normalized<R: Rng + ?Sized>(&self, rng: &mut R) -> Self {
+        let ret = Self {
+            position: self.position / self.position.len(),
+            curvature: self.curvature,
+        };
+        if ret.is_valid() {
+            ret
+        } else {
+            Self::random_unit(self.curvature, rng)
+        }
+    }
+
+    /// Checks whether the `HyperbolicVector` is valid.
+    ///
+    /// In this case, valid means none of the components are NaN or Inf, and the curvature is
+    /// greater than zero.
+    pub(crate) fn is_valid(&self) -> bool {
+        !self.position.is_invalid() && is_valid_curvature(self.curvature)
+    }
+}
+
+//
+// **** Trait Implementations ****
+//
+
+impl<const N: usize, T> Default for HyperbolicVector<N, T>
+where
+    T: Real,
+{
+    /// Default value is the origin of a space with the default curvature.
+    fn default() -> Self {
+        Self {
+            position: Vector::default(),
+            curvature: T::from_f64(DEFAULT_CURVATURE),
+        }
+    }
+}
+
+impl<const N: usize, T> TryFrom<HyperbolicVectorRepr<N, T>> for HyperbolicVector<N, T>
+where
+    T: Real,
+{
+    type Error = CoordinateError;
+
+    /// Validate a deserialized point. The position validates itself.
+    fn try_from(value: HyperbolicVectorRepr<N, T>) -> Result<Self, Self::Error> {
+        if value.model != CoordinateModel::Hyperbolic {
+            return Err(CoordinateError::ModelMismatch);
+        }
+        if is_valid_curvature(value.curvature) {
+            Ok(Self {
+                position: value.position,
+                curvature: value.curvature,
+            })
+        } else {
+            Err(CoordinateError::InvalidCurvature)
+        }
+    }
+}
+
+impl<const N: usize, T> Add for HyperbolicVector<N, T>
+where
+    T: Real,
+{
+    type Output = Self;
+
+    /// Add the spatial coordinates of two vectors.
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            position: self.position + rhs.position,
+            curvature: self.curvature,
+        }
+    }
+}
+
+impl<const N: usize, T> Sub for HyperbolicVector<N, T>
+where
+    T: Real,
+{
+    type Output = Self;
+
+    /// Subtract the spatial coordinates of two vectors.
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            position: self.position - rhs.position,
+            curvature: self.curvature,
+        }
+    }
+}
+
+impl<const N: usize, T> Mul<T> for HyperbolicVector<N, T>
+where
+    T: Real,
+{
+    type Output = Self;
+
+    /// Scale the spatial coordinates of a vector.
+    fn mul(self, rhs: T) -> Self::Output {
+        Self {
+            position: self.position * rhs,
+            curvature: self.curvature,
+        }
+    }
+}
+
+//
+// **** Functions ****
+//
+
+fn is_valid_curvature<T: Real>(curvature: T) -> bool {
+    curvature.is_finite() && curvature > T::zero()
+}
+
+//
+// **** Tests ****
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_new_invalid() {
+        assert_eq!(
+            HyperbolicVector::<2>::new([f64::NAN, 0.0], 1.0),
+            Err(CoordinateError::NonFinitePosition)
+        );
+        assert_eq!(
+            HyperbolicVector::<2>::new([0.0, 0.0], 0.0),
+            Err(CoordinateError::InvalidCurvature)
+        );
+        assert_eq!(
+            HyperbolicVector::<2>::random_with_curvature(-1.0),
+            Err(CoordinateError::InvalidCurvature)
+        );
+        assert!(HyperbolicVector::<2>::new([1.0, 2.0], 1.0).is_ok());
+    }
+
+    #[test]
+    fn test_distance() {
+        // from the origin, the distance is asinh(√c × ‖x‖) / √c
+        let o = HyperbolicVector::<2>::new([0.0, 0.0], 1.0).expect("valid point rejected");
+        let a = HyperbolicVector::<2>::new([3.0, 4.0], 1.0).expect("valid point rejected");
+        let b = HyperbolicVector::<2>::new([-3.0, -4.0], 1.0).expect("valid point rejected");
+        assert_approx_eq!(o.distance(&a), f64::asinh(5.0));
+        assert_approx_eq!(a.distance(&o), f64::asinh(5.0));
+        assert_approx_eq!(a.distance(&a), 0.0);
+
+        // the shortest path between a and b runs through the origin
+        assert_approx_eq!(a.distance(&b), 2.0 * f64::asinh(5.0), 1e-4);
+    }
+
+    #[test]
+    fn test_distance_flat() {
+        // a tiny curvature is almost Euclidean
+        let a = HyperbolicVector::<2>::new([3.0, 4.0], 1e-8).expect("valid point rejected");
+        let b = HyperbolicVector::<2>::new([-3.0, -4.0], 1e-8).expect("valid point rejected");
+        assert_approx_eq!(a.distance(&b), 10.0, 1e-3);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_ops_keep_curvature() {
+        let a = HyperbolicVector::<2>::new([3.0, 4.0], 0.5).expect("valid point rejected");
+        let b = HyperbolicVector::<2>::new([1.0, 1.0], 2.0).expect("valid point rejected");
+        let c = (a + (a - b).normalized(&mut thread_rng()) * 2.0) * 0.5;
+        assert_approx_eq!(c.curvature(), 0.5);
+        assert_approx_eq!(a.normalized(&mut thread_rng()).position.len(), 1.0);
+        assert!(HyperbolicVector::<2>::random_with_curvature(0.5)
+            .expect("valid curvature rejected")
+            .is_valid());
+    }
+
+    #[test]
+    fn test_serde() {
+        let a = HyperbolicVector::<2>::new([1.5, 0.5], 0.25).expect("valid point rejected");
+        let s = serde_json::to_string(&a).expect("serialization failed");
+        assert_eq!(
+            s,
+            "{\"model\":\"hyperbolic\",\"position\":[1.5,0.5],\"curvature\":0.25}"
+        );
+        let b: HyperbolicVector<2> = serde_json::from_str(&s).expect("deserialization failed");
+        assert_eq!(a, b);
+
+        assert!(serde_json::from_str::<HyperbolicVector<2>>(
+            "{\"model\":\"hyperbolic\",\"position\":[1.5,0.5],\"curvature\":-0.25}"
+        )
+        .is_err());
+        assert!(serde_json::from_str::<HyperbolicVector<2>>(
+            "{\"position\":[1.5,0.5],\"curvature\":0.25}"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,343 @@
+//! The geometry a [`NetworkCoordinate`](crate::NetworkCoordinate) is embedded in.
+//!
+//! Vivaldi itself doesn't care much about the shape of the latency space. All it needs is a way
+//! to measure the distance between two points (the estimated RTT), a direction to move in, and a
+//! way to move. [`LatencySpace`] captures exactly that, so
+//! [`NetworkCoordinate`](crate::NetworkCoordinate) can be used with other embedding spaces while
+//! keeping the same update/estimate interface and serde behavior.
+//!
+//! The default space is the Euclidean plus height model from the paper, [`HeightVector`]. Nodes
+//! far away from the core of the network can be embedded with less distortion in hyperbolic space,
+//! using [`HyperbolicVector`], and networks spanning the globe can wrap around a sphere with
+//! [`SphericalVector`].
+//!
+//! # Telling Models Apart
+//!
+//! Every model except [`HeightVector`] adds a `model` field to the serialized coordinate, with the
+//! name of the model as its value. A message without one uses the height vector model, which keeps
+//! the original message format unchanged. Receivers which accept more than one model can peek at
+//! the discriminator first:
+//!
+//! ```
+//! use serde::Deserialize;
+//! use vivaldi_nc::{CoordinateModel, LatencySpace, NetworkCoordinate, SphericalVector};
+//!
+//! #[derive(Deserialize)]
+//! struct Peek {
+//!     #[serde(default)]
+//!     model: CoordinateModel,
+//! }
+//!
+//! let msg = "{\"model\":\"spherical\",\"position\":[0.0,0.0,80.0],\"radius\":80.0,\"error\":1.0}";
+//! let peek: Peek = serde_json::from_str(msg).unwrap();
+//! assert_eq!(peek.model, SphericalVector::<3>::MODEL);
+//! let remote: NetworkCoordinate<3, f64, SphericalVector<3>> = serde_json::from_str(msg).unwrap();
+//! ```
+
+use core::fmt::Debug;
+use core::ops::{Add, Mul, Sub};
+
+use rand::Rng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::config::VivaldiConfig;
+use crate::height_vector::HeightVector;
+use crate::hyperbolic::HyperbolicVector;
+use crate::real::Real;
+use crate::spherical::SphericalVector;
+
+//
+// **** Enums ****
+//
+
+/// The discriminator which tells the [`LatencySpace`] models apart on the wire.
+///
+/// It's serialized in `snake_case`, e.g. `"model":"spherical"`. Deserializing a coordinate with
+/// the wrong model fails with [`CoordinateError::ModelMismatch`](crate::CoordinateError).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoordinateModel {
+    /// [`HeightVector`], the Euclidean plus height model from the Vivaldi paper. It's the default,
+    /// so messages without a `model` field use it.
+    #[default]
+    HeightVector,
+
+    /// [`HyperbolicVector`], a point in hyperbolic space.
+    Hyperbolic,
+
+    /// [`SphericalVector`], a point on the surface of a sphere.
+    Spherical,
+}
+
+//
+// **** Traits ****
+//
+
+/// A latency space which Vivaldi coordinates can be embedded in.
+///
+/// Values of an implementing type are used both as points (the coordinate of a node) and as
+/// vectors between points (the difference of two coordinates, or a force moving a coordinate).
+/// An update works like this, with `xi` and `xj` as the local and remote points:
+///
+/// ```text
+/// xi = xi.moved_away(xj, δ × (rtt − xi.distance(xj))).constrained(config)
+/// ```
+///
+/// In Euclidean like spaces, moving away is simply `xi + (xi - xj).normalized() * distance`.
+///
+/// # Serialization
+///
+/// Implementations are serialized flattened into the
+/// [`NetworkCoordinate`](crate::NetworkCoordinate), next to its `error`. They're expected to
+/// validate themselves while deserializing, because the values usually come from remote nodes.
+///
+/// # Generic Parameters
+///
+/// - `T`: the floating point type distances are computed with
+pub trait LatencySpace<T>:
+    Copy
+    + Debug
+    + Serialize
+    + DeserializeOwned
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<T, Output = Self>
+where
+    T: Real,
+{
+    /// The model this space is serialized as.
+    const MODEL: CoordinateModel;
+
+    /// A random point close to the origin (or wherever the space starts out), drawn from `rng`.
+    /// Used to initialize new coordinates.
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self;
+
+    /// The distance between two points in milliseconds, i.e. the estimated RTT between them.
+    fn distance(&self, rhs: &Self) -> T;
+
+    /// This vector scaled to unit magnitude, or a random unit vector drawn from `rng` if that's not
+    /// possible.
+    #[must_use]
+    fn normalized<R: Rng + ?Sized>(&self, rng: &mut R) -> Self;
+
+    /// Moves `self` by `distance` milliseconds directly away from `rhs`, or toward it if `distance`
+    /// is negative. The default moves along `(self - rhs).normalized()`, which is right for
+    /// Euclidean like spaces.
+    ///
+    /// Any randomness, e.g. picking a direction when the points coincide, is drawn from `rng`.
+    #[must_use]
+    fn moved_away<R: Rng + ?Sized>(&self, rhs: &Self, distance: T, rng: &mut R) -> Self {
+        *self + (*self - *rhs).normalized(rng) * distance
+    }
+
+    /// How far a point moved from `previous` to `self`, in milliseconds. The default is the
+    /// distance between them, which is right for spaces where a point is no distance away from
+    /// itself.
+    fn displacement(&self, previous: &Self) -> T {
+        self.distance(previous)
+    }
+
+    /// Whether this is a valid point in the space, e.g. no NaN or Inf components.
+    fn is_valid(&self) -> bool;
+
+    /// Applies the space specific parts of `config` after a point has been moved, like clamping
+    /// the height of a [`HeightVector`]. The default does nothing.
+    #[must_use]
+    fn constrained(self, _config: &VivaldiConfig<T>) -> Self {
+        self
+    }
+}
+
+//
+// **** Trait Implementations ****
+//
+
+impl<const N: usize, T> LatencySpace<T> for HeightVector<N, T>
+where
+    T: Real,
+{
+    const MODEL: CoordinateModel = CoordinateModel::HeightVector;
+
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::random(rng)
+    }
+
+    /// The Euclidean distance between the positions plus both heights.
+    fn distance(&self, rhs: &Self) -> T {
+        (*self - *rhs).len()
+    }
+
+    fn normalized<R: Rng + ?Sized>(&self, rng: &mut R) -> Self {
+        Self::normalized(self, rng)
+    }
+
+    /// The distance between the positions plus the change in height. The height vector distance
+    /// can't be used, because it adds both heights.
+    fn displacement(&self, previous: &Self) -> T {
+        Self::displacement(self, previous)
+    }
+
+    fn is_valid(&self) -> bool {
+        Self::is_valid(self)
+    }
+
+    /// Clamps the height to the configured minimum, and applies gravity if it's enabled.
+    fn constrained(self, config: &VivaldiConfig<T>) -> Self {
+        let ret = self.with_min_height(config.min_height());
+        config
+            .gravity_rho()
+            .map_or(ret, |rho| ret.with_gravity(rho))
+    }
+}
+
+impl<const N: usize, T> LatencySpace<T> for HyperbolicVector<N, T>
+where
+    T: Real,
+{
+    const MODEL: CoordinateModel = CoordinateModel::Hyperbolic;
+
+    /// A random unit vector in a space with the default curvature.
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::random_unit(Self::default().curvature(), rng)
+    }
+
+    /// The hyperbolic distance between the points.
+    fn distance(&self, rhs: &Self) -> T {
+        Self::distance(self, rhs)
+    }
+
+    fn normalized<R: Rng + ?Sized>(&self, rng: &mut R) -> Self {
+        Self::normalized(self, rng)
+    }
+
+    /// Moves along the geodesic through both points.
+    fn moved_away<R: Rng + ?Sized>(&self, rhs: &Self, distance: T, rng: &mut R) -> Self {
+        Self::moved_away(self, rhs, distance, rng)
+    }
+
+    fn is_valid(&self) -> bool {
+        Self::is_valid(self)
+    }
+}
+
+impl<const N: usize, T> LatencySpace<T> for SphericalVector<N, T>
+where
+    T: Real,
+{
+    const MODEL: CoordinateModel = CoordinateModel::Spherical;
+
+    /// A random point on a sphere with the default radius.
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::random(rng)
+    }
+
+    /// The great circle distance between the points.
+    fn distance(&self, rhs: &Self) -> T {
+        Self::distance(self, rhs)
+    }
+
+    fn normalized<R: Rng + ?Sized>(&self, rng: &mut R) -> Self {
+        Self::normalized(self, rng)
+    }
+
+    /// Moves along the great circle through both points.
+    fn moved_away<R: Rng + ?Sized>(&self, rhs: &Self, distance: T, rng: &mut R) -> Self {
+        Self::moved_away(self, rhs, distance, rng)
+    }
+
+    fn is_valid(&self) -> bool {
+        Self::is_valid(self)
+    }
+
+    /// Projects the point back onto the sphere, in case rounding errors moved it off.
+    fn constrained(self, _config: &VivaldiConfig<T>) -> Self {
+        self.projected()
+    }
+}
+
+//
+// **** Tests ****
+//
+#[cfg(test)]
+mod tests {
+    use assert_approx_eq::assert_approx_eq;
+    use rand::thread_rng;
+
+    use super::*;
+
+    /// Exercise a space only through the trait, the way `NetworkCoordinate` does.
+    fn distance_after_move<S: LatencySpace<f64>>(a: S, b: S, by: f64) -> f64 {
+        let config = VivaldiConfig::default();
+        let moved = a.moved_away(&b, by, &mut thread_rng()).constrained(&config);
+        moved.distance(&b)
+    }
+
+    #[test]
+    fn test_height_vector_distance() {
+        let a = HeightVector::<2>::new([0.0, 0.0], 1.0).expect("valid point rejected");
+        let b = HeightVector::<2>::new([3.0, 4.0], 2.0).expect("valid point rejected");
+        assert_approx_eq!(LatencySpace::distance(&a, &b), 8.0);
+        assert_approx_eq!(LatencySpace::distance(&b, &a), 8.0);
+    }
+
+    #[test]
+    fn test_height_vector_move() {
+        let a = HeightVector::<2>::new([0.0, 0.0], 1.0).expect("valid point rejected");
+        let b = HeightVector::<2>::new([3.0, 4.0], 2.0).expect("valid point rejected");
+        assert!(distance_after_move(a, b, 2.0) > 8.0);
+        assert!(distance_after_move(a, b, -2.0) < 8.0);
+    }
+
+    #[test]
+    fn test_displacement() {
+        // a point hasn't moved from itself, even though it's `2 × height` away from itself
+        let a = HeightVector::<2>::new([0.0, 0.0], 1.0).expect("valid point rejected");
+        let b = HeightVector::<2>::new([3.0, 4.0], 3.0).expect("valid point rejected");
+        assert_approx_eq!(a.displacement(&a), 0.0);
+        assert_approx_eq!(b.displacement(&a), 7.0);
+
+        let a = SphericalVector::<3>::new([1.0, 0.0, 0.0], 80.0).expect("valid point rejected");
+        assert_approx_eq!(LatencySpace::displacement(&a, &a), 0.0);
+    }
+
+    #[test]
+    fn test_height_vector_constrained() {
+        let config = VivaldiConfig::builder()
+            .min_height(1.0)
+            .gravity_rho(Some(1.0))
+            .build()
+            .expect("valid config rejected during test");
+        let a = HeightVector::<2>::new([3.0, 4.0], 0.0)
+            .expect("valid point rejected")
+            .constrained(&config);
+        assert!(LatencySpace::is_valid(&a));
+        assert_approx_eq!(a.len(), 1.0);
+    }
+
+    #[test]
+    fn test_hyperbolic_move() {
+        let a = HyperbolicVector::<2>::new([0.0, 0.0], 1e-3).expect("valid point rejected");
+        let b = HyperbolicVector::<2>::new([30.0, 40.0], 1e-3).expect("valid point rejected");
+        let d = LatencySpace::distance(&a, &b);
+        assert!(distance_after_move(a, b, 2.0) > d);
+        assert!(distance_after_move(a, b, -2.0) < d);
+    }
+
+    #[test]
+    fn test_spherical_move() {
+        let a = SphericalVector::<3>::new([1.0, 0.0, 0.0], 80.0).expect("valid point rejected");
+        let b = SphericalVector::<3>::new([0.0, 1.0, 0.0], 80.0).expect("valid point rejected");
+        let d = LatencySpace::distance(&a, &b);
+        assert_approx_eq!(distance_after_move(a, b, 2.0), d + 2.0, 1e-3);
+        assert_approx_eq!(distance_after_move(a, b, -2.0), d - 2.0, 1e-3);
+    }
+
+    #[test]
+    fn test_model_serde() {
+        assert_eq!(
+            serde_json::to_string(&SphericalVector::<3>::MODEL).expect("serialization failed"),
+            "\"spherical\""
+        );
+        assert_eq!(CoordinateModel::default(), HeightVector::<3>::MODEL);
+    }
+}
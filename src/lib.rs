@@ -20,9 +20,14 @@
 //!
 //! # Usage
 //!
-//! This crate exports a single struct, [`NetworkCoordinate`] and two type aliases
-//! ([`NetworkCoordinate2D`] and [`NetworkCoordinate3D`]). Typical use of Vivaldi NCs for a
-//! distributed network works like this:
+//! The main interface of this crate is a single struct, [`NetworkCoordinate`] and two type aliases
+//! ([`NetworkCoordinate2D`] and [`NetworkCoordinate3D`]). The Vivaldi tuning parameters can be
+//! adjusted at runtime with a [`VivaldiConfig`], and noisy RTT samples can be smoothed with a
+//! [`LatencyFilter`] before they're used. Coordinates live in the Euclidean plus height space from
+//! the paper ([`HeightVector`]) by default, other geometries (like the hyperbolic
+//! [`HyperbolicVector`] or the spherical [`SphericalVector`]) can be plugged in through the
+//! [`LatencySpace`] trait. Typical use of Vivaldi NCs for a distributed network
+//! works like this:
 //!
 //! 1. Each node in the network has its own instance of [`NetworkCoordinate`]. *See "Note on
 //!    dimensionality" below.*
@@ -40,12 +45,24 @@
 //! `NetworkCoordinate<3>`) are sufficient. Higher dimensions don't add much accuracy and aren't
 //! generally worth it. That said, you're welcome to use any dimension you like.
 //!
+//! **Note on precision:** Coordinates compute with `f64` by default. The float type is a type
+//! parameter (any [`Real`], i.e. `f32` or `f64`), so e.g. `NetworkCoordinate<3, f32>` can be used
+//! where memory matters more than precision.
+//!
+//! **Note on `no_std`:** The `std` feature is enabled by default. Without it, the crate only needs
+//! `core` and `alloc`. Everything which would use the thread local RNG (like
+//! [`NetworkCoordinate::new`] or [`NetworkCoordinate::update`]) is unavailable, use
+//! [`NetworkCoordinate::new_with_rng`] and [`NetworkCoordinate::update_with_rng`] with your own RNG
+//! instead. [`LatencyFilter`] requires `std`.
+//!
 //! # Examples
 //!
 //! In the first example, we simulate receving a remote NC, measuring ping, and updating our NC
 //! accordingly.
 //!
 //! ```
+//! # #[cfg(feature = "std")]
+//! # fn main() {
 //! use vivaldi_nc::NetworkCoordinate;
 //!
 //! // create our local NC as a 3-dimensional Vivaldi coordinate
@@ -65,6 +82,9 @@
 //!
 //! // by doing this iteratively (and forever), all NCs in the network will converge to better
 //! // represent the actual latency space of the network
+//! # }
+//! # #[cfg(not(feature = "std"))]
+//! # fn main() {}
 //! ```
 //!
 //! In this second example, we simulate receving a NC from another node indirectly; likely
@@ -75,6 +95,8 @@
 //! [`NetworkCoordinate<3>`].
 //!
 //! ```
+//! # #[cfg(feature = "std")]
+//! # fn main() {
 //! use vivaldi_nc::NetworkCoordinate3D;
 //!
 //! // create our local NC as a 3-dimensional Vivaldi coordinate
@@ -90,9 +112,13 @@
 //!
 //! // so with just the knowledge of another NC, we can get reasonably good estimates of RTT
 //! // without actually needing to measure it
+//! # }
+//! # #[cfg(not(feature = "std"))]
+//! # fn main() {}
 //! ```
 //!
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![deny(
     clippy::all,
     clippy::correctness,
@@ -121,13 +147,56 @@
     variant_size_differences
 )]
 #![allow(clippy::type_repetition_in_bounds)]
+#![allow(clippy::multiple_crate_versions)]
 #![allow(single_use_lifetimes)]
 
+extern crate alloc;
+
 mod height_vector;
+mod hyperbolic;
+mod spherical;
 mod vector;
 
 // publish our interface
+pub mod config;
+#[cfg(feature = "datasets")]
+pub mod datasets;
+pub mod error;
+#[cfg(feature = "std")]
+pub mod filter;
+pub mod latency_space;
+#[cfg(feature = "std")]
+pub mod metrics;
 pub mod network_coordinate;
+pub mod real;
+#[cfg(feature = "std")]
+pub mod simulator;
+#[cfg(feature = "std")]
+pub mod solver;
+pub use config::UpdateRule;
+pub use config::VivaldiConfig;
+pub use config::VivaldiConfigBuilder;
+pub use error::ConfigError;
+pub use error::CoordinateError;
+#[cfg(feature = "datasets")]
+pub use error::DatasetError;
+#[cfg(feature = "std")]
+pub use error::SolverError;
+pub use error::UpdateError;
+#[cfg(feature = "std")]
+pub use filter::LatencyFilter;
+pub use height_vector::HeightVector;
+pub use hyperbolic::HyperbolicVector;
+pub use latency_space::CoordinateModel;
+pub use latency_space::LatencySpace;
 pub use network_coordinate::NetworkCoordinate;
 pub use network_coordinate::NetworkCoordinate2D;
 pub use network_coordinate::NetworkCoordinate3D;
+pub use network_coordinate::RttEstimate;
+pub use network_coordinate::UpdateOutcome;
+pub use real::Real;
+#[cfg(feature = "std")]
+pub use solver::solve_from_matrix;
+#[cfg(feature = "std")]
+pub use solver::SolverOptions;
+pub use spherical::SphericalVector;
@@ -0,0 +1,510 @@
+//! Accuracy and stability metrics for evaluating coordinates.
+//!
+//! A node's self-reported [`error`](crate::NetworkCoordinate::error) only says how well its
+//! coordinate has fit the samples it has seen. To find out how well coordinates actually predict
+//! RTTs, e.g. at the end of a [`Simulator`](crate::simulator::Simulator) run, compare their
+//! estimates to the measured RTTs with an [`AccuracyReport`].
+//!
+//! Applications which cache coordinates also care about how much they move. A
+//! [`StabilityReport`] compares two [`Snapshot`]s of the same nodes, taken from a simulator or
+//! from logs of a live system.
+//!
+//! # Example
+//!
+//! ```
+//! use core::time::Duration;
+//! use vivaldi_nc::metrics::AccuracyReport;
+//! use vivaldi_nc::NetworkCoordinate;
+//!
+//! let nc: Vec<NetworkCoordinate<2>> = vec![
+//!     NetworkCoordinate::from_parts([0.0, 0.0], 0.0, 0.1).unwrap(),
+//!     NetworkCoordinate::from_parts([30.0, 0.0], 0.0, 0.1).unwrap(),
+//! ];
+//! let ms = |ms| Some(Duration::from_millis(ms));
+//! let truth = vec![vec![None, ms(25)], vec![ms(25), None]];
+//!
+//! let report = AccuracyReport::new(&nc, &truth).unwrap();
+//! assert!((report.median_relative_error() - 0.2).abs() < 1e-9);
+//! ```
+
+use core::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::height_vector::HeightVector;
+use crate::latency_space::LatencySpace;
+use crate::network_coordinate::NetworkCoordinate;
+use crate::real::Real;
+use crate::simulator::LatencySource;
+
+//
+// **** Structs ****
+//
+
+/// How well a set of coordinates predicts the RTTs between their nodes.
+///
+/// Only pairs of distinct nodes with a measured RTT count. Throughout, `m` is the measured RTT and
+/// `e` the estimated RTT of a pair, and the relative error of a pair is `|e - m| / m`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccuracyReport {
+    // relative errors of all pairs, sorted
+    relative_errors: Vec<f64>,
+    rela: f64,
+    stress: f64,
+    closest_neighbor_loss: Duration,
+    closest_neighbor_accuracy: f64,
+}
+
+/// The coordinates of all nodes of a network at one point in time.
+///
+/// Snapshots serialize to a `time` and an array of `coordinates`, so they can be logged by a live
+/// system and evaluated later.
+///
+/// ## Generic Parameters
+///
+/// - `N`: the dimensionality of the coordinates
+/// - `T`: the floating point type of the coordinates, `f64` by default
+/// - `S`: the [`LatencySpace`] of the coordinates, [`HeightVector`] by default
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T: Real, S: LatencySpace<T>",
+    deserialize = "T: Real, S: LatencySpace<T>"
+))]
+pub struct Snapshot<const N: usize, T = f64, S = HeightVector<N, T>>
+where
+    T: Real,
+    S: LatencySpace<T>,
+{
+    time: Duration,
+    coordinates: Vec<NetworkCoordinate<N, T, S>>,
+}
+
+/// How much coordinates moved between two [`Snapshot`]s.
+///
+/// All rates are in milliseconds (of coordinate space) per second, so snapshots taken at
+/// different intervals can be compared. How far a point moved is its
+/// [`LatencySpace::displacement`], e.g. including the change of its height for a
+/// [`HeightVector`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct StabilityReport {
+    displacement_rates: Vec<f64>,
+    instability: f64,
+    centroid_drift: f64,
+}
+
+//
+// **** Implementations ****
+//
+
+impl AccuracyReport {
+    /// Compares the estimated RTTs between `coordinates` to the RTTs of `truth`, where
+    /// `coordinates[i]` belongs to node `i` of `truth`. Time varying sources are compared at
+    /// round 0.
+    ///
+    /// # Returns
+    ///
+    /// - the report, or `None` if the number of coordinates doesn't match the size of `truth`, or
+    ///   `truth` has no measured RTTs between distinct nodes
+    #[must_use]
+    pub fn new<const N: usize, T, S, L>(
+        coordinates: &[NetworkCoordinate<N, T, S>],
+        truth: &L,
+    ) -> Option<Self>
+    where
+        T: Real,
+        S: LatencySpace<T>,
+        L: LatencySource + ?Sized,
+    {
+        if coordinates.len() != truth.size() {
+            return None;
+        }
+
+        let mut relative_errors = Vec::new();
+        let mut absolute_error = 0.0;
+        let mut squared_error = 0.0;
+        let mut measured_sum = 0.0;
+        let mut squared_measured = 0.0;
+        let mut loss = Duration::ZERO;
+        let mut hits = 0_u32;
+        let mut nodes = 0_u32;
+
+        for (i, local) in coordinates.iter().enumerate() {
+            // (measured, estimated) RTT of the actual and of the predicted closest neighbor
+            let mut actual_closest: Option<(Duration, Duration)> = None;
+            let mut predicted_closest: Option<(Duration, Duration)> = None;
+
+            for (j, remote) in coordinates.iter().enumerate() {
+                let Some(measured) = truth.rtt(i, j, 0).filter(|rtt| i != j && !rtt.is_zero())
+                else {
+                    continue;
+                };
+                let estimated = local.estimated_rtt(remote);
+
+                let (m, e) = (measured.as_secs_f64(), estimated.as_secs_f64());
+                relative_errors.push((e - m).abs() / m);
+                absolute_error += (e - m).abs();
+                squared_error += (e - m) * (e - m);
+                measured_sum += m;
+                squared_measured += m * m;
+
+                if actual_closest.map_or(true, |(closest, _)| measured < closest) {
+                    actual_closest = Some((measured, estimated));
+                }
+                if predicted_closest.map_or(true, |(_, closest)| estimated < closest) {
+                    predicted_closest = Some((measured, estimated));
+                }
+            }
+
+            if let (Some((actual, _)), Some((predicted, _))) = (actual_closest, predicted_closest) {
+                nodes += 1;
+                // the predicted closest neighbor is never actually closer than the closest one
+                loss += predicted.saturating_sub(actual);
+                if predicted == actual {
+                    hits += 1;
+                }
+            }
+        }
+
+        if relative_errors.is_empty() {
+            return None;
+        }
+        relative_errors.sort_by(f64::total_cmp);
+
+        Some(Self {
+            relative_errors,
+            rela: absolute_error / measured_sum,
+            stress: (squared_error / squared_measured).sqrt(),
+            closest_neighbor_loss: loss / nodes,
+            closest_neighbor_accuracy: f64::from(hits) / f64::from(nodes),
+        })
+    }
+
+    /// The number of pairs compared.
+    #[must_use]
+    pub fn pairs(&self) -> usize {
+        self.relative_errors.len()
+    }
+
+    /// The mean relative error over all pairs.
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn mean_relative_error(&self) -> f64 {
+        self.relative_errors.iter().sum::<f64>() / self.relative_errors.len() as f64
+    }
+
+    /// The median relative error over all pairs.
+    #[must_use]
+    pub fn median_relative_error(&self) -> f64 {
+        self.percentile_relative_error(0.5)
+    }
+
+    /// The nearest rank `p` percentile of the relative errors of all pairs, with `p` in `[0, 1]`
+    /// (e.g. `0.9` for the 90th percentile). Values outside of that range are clamped.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    #[must_use]
+    pub fn percentile_relative_error(&self, p: f64) -> f64 {
+        let last = self.relative_errors.len() - 1;
+        let rank = (p.clamp(0.0, 1.0) * last as f64).round() as usize;
+        self.relative_errors[rank.min(last)]
+    }
+
+    /// RELA, the relative error of latency approximation: the summed absolute errors relative to
+    /// the summed RTTs, `Σ|e - m| / Σm`. Unlike the mean relative error, it isn't dominated by
+    /// small RTTs.
+    #[must_use]
+    pub const fn rela(&self) -> f64 {
+        self.rela
+    }
+
+    /// The stress of the embedding, `sqrt(Σ(e - m)² / Σm²)`, where 0 is a perfect fit.
+    #[must_use]
+    pub const fn stress(&self) -> f64 {
+        self.stress
+    }
+
+    /// The closest neighbor loss: how much higher, on average over all nodes, the measured RTT to
+    /// the node with the lowest estimated RTT is than the RTT to the actually closest node. This
+    /// is the latency penalty of picking the closest node by coordinates.
+    #[must_use]
+    pub const fn closest_neighbor_loss(&self) -> Duration {
+        self.closest_neighbor_loss
+    }
+
+    /// The fraction of nodes for which the node with the lowest estimated RTT is the actually
+    /// closest node (or one at the same RTT), in `[0, 1]`.
+    #[must_use]
+    pub const fn closest_neighbor_accuracy(&self) -> f64 {
+        self.closest_neighbor_accuracy
+    }
+}
+
+impl<const N: usize, T, S> Snapshot<N, T, S>
+where
+    T: Real,
+    S: LatencySpace<T>,
+{
+    /// Creates a snapshot of `coordinates` taken at `time`, which can be measured from any
+    /// reference point (e.g. the start of a simulation, or the UNIX epoch), as long as it's the
+    /// same for all snapshots being compared.
+    #[must_use]
+    pub const fn new(time: Duration, coordinates: Vec<NetworkCoordinate<N, T, S>>) -> Self {
+        Self { time, coordinates }
+    }
+
+    /// When the snapshot was taken.
+    #[must_use]
+    pub const fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// The coordinates of all nodes.
+    #[must_use]
+    pub fn coordinates(&self) -> &[NetworkCoordinate<N, T, S>] {
+        &self.coordinates
+    }
+
+    /// The mean point of all nodes, i.e. the sum of their points scaled by `1 / n` (for a
+    /// [`HeightVector`], the mean position and the mean height), or `None` if there are none.
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn centroid(&self) -> Option<S> {
+        let (first, rest) = self.coordinates.split_first()?;
+        let sum = rest
+            .iter()
+            .fold(*first.point(), |sum, nc| sum + *nc.point());
+        Some(sum * T::from_f64(1.0 / self.coordinates.len() as f64))
+    }
+}
+
+impl StabilityReport {
+    /// Compares two snapshots of the same nodes, where `coordinates()[i]` belongs to the same node
+    /// in both.
+    ///
+    /// A series of snapshots gives one report per interval, e.g. with
+    /// `snapshots.windows(2).filter_map(|w| StabilityReport::new(&w[0], &w[1]))`.
+    ///
+    /// # Returns
+    ///
+    /// - the report, or `None` if the snapshots have a different number of nodes, no nodes, or
+    ///   `current` wasn't taken after `previous`
+    #[must_use]
+    pub fn new<const N: usize, T, S>(
+        previous: &Snapshot<N, T, S>,
+        current: &Snapshot<N, T, S>,
+    ) -> Option<Self>
+    where
+        T: Real,
+        S: LatencySpace<T>,
+    {
+        if previous.coordinates.len() != current.coordinates.len() {
+            return None;
+        }
+        let elapsed = current
+            .time
+            .checked_sub(previous.time)
+            .filter(|elapsed| !elapsed.is_zero())?
+            .as_secs_f64();
+
+        let displacement_rates: Vec<f64> = previous
+            .coordinates
+            .iter()
+            .zip(&current.coordinates)
+            .map(|(previous, current)| {
+                current.point().displacement(previous.point()).as_f64() / elapsed
+            })
+            .collect();
+        let drift = current
+            .centroid()?
+            .displacement(&previous.centroid()?)
+            .as_f64();
+
+        Some(Self {
+            instability: displacement_rates.iter().sum(),
+            displacement_rates,
+            centroid_drift: drift / elapsed,
+        })
+    }
+
+    /// How fast each node moved, in the order of the snapshots' coordinates.
+    #[must_use]
+    pub fn displacement_rates(&self) -> &[f64] {
+        &self.displacement_rates
+    }
+
+    /// How fast nodes moved on average.
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn mean_displacement_rate(&self) -> f64 {
+        self.instability / self.displacement_rates.len() as f64
+    }
+
+    /// The instability of the whole system as defined by Ledlie et al., the sum of how fast all
+    /// nodes moved. Zero means no coordinate changed.
+    #[must_use]
+    pub const fn instability(&self) -> f64 {
+        self.instability
+    }
+
+    /// How fast the centroid of all points ([`Snapshot::centroid`]) moved. Vivaldi coordinates
+    /// only make sense relative to each other, so this is movement that doesn't make estimates any
+    /// better, and which invalidates cached coordinates for nothing.
+    #[must_use]
+    pub const fn centroid_drift(&self) -> f64 {
+        self.centroid_drift
+    }
+}
+
+//
+// **** Tests ****
+//
+#[cfg(test)]
+mod tests {
+    use assert_approx_eq::assert_approx_eq;
+
+    use super::*;
+    use crate::spherical::SphericalVector;
+
+    fn nc(x: f64) -> NetworkCoordinate<2> {
+        NetworkCoordinate::from_parts([x, 0.0], 0.0, 0.1).expect("valid coordinate rejected")
+    }
+
+    /// RTTs in ms, where zero is missing.
+    fn truth(ms: &[[u64; 3]]) -> Vec<Vec<Option<Duration>>> {
+        ms.iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&ms| Some(Duration::from_millis(ms)).filter(|rtt| !rtt.is_zero()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_perfect() {
+        let coordinates = [nc(0.0), nc(10.0), nc(30.0)];
+        let truth = truth(&[[0, 10, 30], [10, 0, 20], [30, 20, 0]]);
+        let report = AccuracyReport::new(&coordinates, &truth).expect("no pairs during test");
+        assert_eq!(report.pairs(), 6);
+        assert_approx_eq!(report.mean_relative_error(), 0.0);
+        assert_approx_eq!(report.percentile_relative_error(1.0), 0.0);
+        assert_approx_eq!(report.rela(), 0.0);
+        assert_approx_eq!(report.stress(), 0.0);
+        assert_eq!(report.closest_neighbor_loss(), Duration::ZERO);
+        assert_approx_eq!(report.closest_neighbor_accuracy(), 1.0);
+    }
+
+    #[test]
+    fn test_errors() {
+        // node 2 is actually closer to node 0 than node 1 is, but not by its coordinates
+        let coordinates = [nc(0.0), nc(10.0), nc(16.0)];
+        let truth = truth(&[[0, 10, 8], [10, 0, 6], [8, 6, 0]]);
+        let report = AccuracyReport::new(&coordinates, &truth).expect("no pairs during test");
+        assert_eq!(report.pairs(), 6);
+
+        // relative errors are 0, 1 and 0 in both directions
+        assert_approx_eq!(report.mean_relative_error(), 1.0 / 3.0);
+        assert_approx_eq!(report.median_relative_error(), 0.0);
+        assert_approx_eq!(report.percentile_relative_error(0.0), 0.0);
+        assert_approx_eq!(report.percentile_relative_error(1.0), 1.0);
+        assert_approx_eq!(report.percentile_relative_error(7.0), 1.0);
+        assert_approx_eq!(report.rela(), 16.0 / 48.0);
+        assert_approx_eq!(report.stress(), (128.0_f64 / 400.0).sqrt());
+
+        // node 0 picks node 1 at 10ms instead of node 2 at 8ms, the others pick right
+        assert_eq!(report.closest_neighbor_loss(), Duration::from_millis(2) / 3);
+        assert_approx_eq!(report.closest_neighbor_accuracy(), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let coordinates = [nc(0.0), nc(10.0), nc(20.0)];
+        assert!(AccuracyReport::new(&coordinates, &truth(&[[0; 3]; 3])).is_none());
+        assert!(AccuracyReport::new(&coordinates[..2], &truth(&[[10; 3]; 3])).is_none());
+    }
+
+    #[test]
+    fn test_stability() {
+        let at = |secs, nc: Vec<NetworkCoordinate<2>>| Snapshot::new(Duration::from_secs(secs), nc);
+        let previous = at(10, vec![nc(0.0), nc(10.0)]);
+        let centroid = previous.centroid().expect("no centroid during test");
+        assert_eq!((centroid.position(), centroid.height()), ([5.0, 0.0], 0.0));
+
+        // over 2 seconds, the first node moves by 4ms, and the centroid by 2ms
+        let current = at(12, vec![nc(4.0), nc(10.0)]);
+        let report = StabilityReport::new(&previous, &current).expect("valid snapshots rejected");
+        assert_eq!(report.displacement_rates().len(), 2);
+        assert_approx_eq!(report.displacement_rates()[0], 2.0);
+        assert_approx_eq!(report.displacement_rates()[1], 0.0);
+        assert_approx_eq!(report.mean_displacement_rate(), 1.0);
+        assert_approx_eq!(report.instability(), 2.0);
+        assert_approx_eq!(report.centroid_drift(), 1.0);
+
+        // everything moves along, the estimates don't change but the centroid drifts
+        let current = at(11, vec![nc(3.0), nc(13.0)]);
+        let report = StabilityReport::new(&previous, &current).expect("valid snapshots rejected");
+        assert_approx_eq!(report.instability(), 6.0);
+        assert_approx_eq!(report.centroid_drift(), 3.0);
+
+        // a height change counts as movement, of the centroid too
+        let tall =
+            NetworkCoordinate::from_parts([0.0, 0.0], 1.0, 0.1).expect("valid coordinate rejected");
+        let current = at(11, vec![tall, nc(10.0)]);
+        let report = StabilityReport::new(&previous, &current).expect("valid snapshots rejected");
+        assert_approx_eq!(report.instability(), 1.0);
+        assert_approx_eq!(report.centroid_drift(), 0.5);
+    }
+
+    #[test]
+    fn test_stability_spherical() {
+        let on_sphere = |position| {
+            let point = SphericalVector::<3>::new(position, 100.0).expect("valid point rejected");
+            NetworkCoordinate::<3, f64, _>::from_point(point)
+                .expect("valid point rejected during test")
+        };
+        let previous = Snapshot::new(
+            Duration::from_secs(1),
+            vec![on_sphere([1.0, 0.0, 0.0]), on_sphere([0.0, 1.0, 0.0])],
+        );
+
+        // the first node moves a quarter of the way around the sphere in 2 seconds
+        let current = Snapshot::new(
+            Duration::from_secs(3),
+            vec![on_sphere([0.0, 0.0, 1.0]), on_sphere([0.0, 1.0, 0.0])],
+        );
+        let report = StabilityReport::new(&previous, &current).expect("valid snapshots rejected");
+        let quarter = core::f64::consts::FRAC_PI_2 * 100.0;
+        assert_approx_eq!(report.displacement_rates()[0], quarter / 2.0);
+        assert_approx_eq!(report.displacement_rates()[1], 0.0);
+        // the centroid moves by 60° on the sphere, from between x and y to between y and z
+        assert_approx_eq!(report.centroid_drift(), quarter * 2.0 / 3.0 / 2.0);
+    }
+
+    #[test]
+    fn test_stability_invalid() {
+        let at = |secs, nc: Vec<NetworkCoordinate<2>>| Snapshot::new(Duration::from_secs(secs), nc);
+        let previous = at(10, vec![nc(0.0), nc(10.0)]);
+        assert!(StabilityReport::new(&previous, &previous).is_none());
+        assert!(StabilityReport::new(&previous, &at(11, vec![nc(0.0)])).is_none());
+        assert!(StabilityReport::new(&at(0, vec![]), &at(1, vec![])).is_none());
+        assert!(at(0, vec![]).centroid().is_none());
+    }
+
+    #[test]
+    fn test_snapshot_serde() {
+        let snapshot = Snapshot::new(Duration::from_millis(1500), vec![nc(1.0)]);
+        let json = serde_json::to_string(&snapshot).expect("serialization failed during test");
+        assert_eq!(
+            json,
+            "{\"time\":{\"secs\":1,\"nanos\":500000000},\
+             \"coordinates\":[{\"position\":[1.0,0.0],\"height\":0.0,\"error\":0.1}]}"
+        );
+        let snapshot: Snapshot<2> =
+            serde_json::from_str(&json).expect("deserialization failed during test");
+        assert_eq!(snapshot.time(), Duration::from_millis(1500));
+        assert_eq!(snapshot.coordinates().len(), 1);
+    }
+}
@@ -2,34 +2,19 @@
 //!
 //! For usage explanation and examples, please see the main [`crate`] documentation.
 
+use alloc::vec::Vec;
 use core::time::Duration;
 
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::{Rng, RngCore};
 use serde::{Deserialize, Serialize};
 
+use crate::config::VivaldiConfig;
+use crate::error::{CoordinateError, UpdateError};
 use crate::height_vector::HeightVector;
-
-//
-// **** Features ****
-//
-#[cfg(feature = "f32")]
-type FloatType = f32;
-
-#[cfg(not(feature = "f32"))]
-type FloatType = f64;
-
-//
-// **** Constants ****
-//
-
-// Vivaldi tuning parameters
-const C_ERROR: FloatType = 0.25;
-const C_DELTA: FloatType = 0.25;
-
-// initial error value
-const DEFAULT_ERROR: FloatType = 200.0;
-
-// error should always be greater than zero
-const MIN_ERROR: FloatType = FloatType::EPSILON;
+use crate::latency_space::LatencySpace;
+use crate::real::Real;
 
 //
 // **** Structs ****
@@ -40,8 +25,13 @@ const MIN_ERROR: FloatType = FloatType::EPSILON;
 /// # Generic Parameters
 ///
 /// - `N`: Const generic for number of dimensions. For example, `NetworkCoordinate<3>` is a
-/// 3-Dimentionsal Euclidean coordinate plus a height. Should be a positive number greater than
-/// zero.
+///   3-Dimentionsal Euclidean coordinate plus a height. Should be a positive number greater than
+///   zero.
+/// - `T`: The floating point type to compute with, `f64` by default. For example,
+///   `NetworkCoordinate<3, f32>` uses half the memory of a `NetworkCoordinate<3>`. Coordinates with
+///   different float types can be used side by side.
+/// - `S`: The [`LatencySpace`] the coordinate is embedded in. Defaults to the Euclidean plus
+///   height model from the paper, [`HeightVector<N, T>`].
 ///
 /// **Note:** Dimensions other than 2D or 3D are usually not useful. If you want to use one of
 /// those dimensions, you can use type aliases ([`NetworkCoordinate2D`] or [`NetworkCoordinate3D`])
@@ -50,11 +40,126 @@ const MIN_ERROR: FloatType = FloatType::EPSILON;
 /// # Examples
 ///
 /// For an explanation and examples of usage, please see the main [`crate`] documentation.
+///
+/// # Deserialization
+///
+/// Deserialized coordinates are validated, so a malformed or hostile message from a remote node
+/// is rejected with a descriptive error instead of corrupting the local coordinate. A valid
+/// coordinate has finite position components, a finite and non-negative height, a finite error
+/// greater than zero, and (if present) a finite adjustment.
+///
+/// ```
+/// use vivaldi_nc::NetworkCoordinate;
+///
+/// let msg = "{\"position\":[1.5,0.5,2.0],\"height\":0.1,\"error\":0.0}";
+/// assert!(serde_json::from_str::<NetworkCoordinate<3>>(msg).is_err());
+/// ```
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct NetworkCoordinate<const N: usize> {
+#[serde(
+    try_from = "NetworkCoordinateRepr<N, T, S>",
+    bound(
+        serialize = "T: Real, S: LatencySpace<T>",
+        deserialize = "T: Real, S: LatencySpace<T>"
+    )
+)]
+pub struct NetworkCoordinate<const N: usize, T = f64, S = HeightVector<N, T>>
+where
+    T: Real,
+    S: LatencySpace<T>,
+{
     #[serde(flatten)]
-    heightvec: HeightVector<N>,
-    error: FloatType,
+    point: S,
+    error: T,
+
+    /// Serf style adjustment term, only present when enabled with
+    /// [`VivaldiConfigBuilder::adjustment_window`](crate::VivaldiConfigBuilder::adjustment_window)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    adjustment: Option<T>,
+
+    /// ring buffer of recent residuals used to compute `adjustment`, these are local only
+    #[serde(skip)]
+    adjustment_samples: Vec<T>,
+    #[serde(skip)]
+    adjustment_index: usize,
+}
+
+/// Unvalidated wire representation of a [`NetworkCoordinate`], only used for deserialization.
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "T: Real, S: LatencySpace<T>"))]
+struct NetworkCoordinateRepr<const N: usize, T, S>
+where
+    T: Real,
+    S: LatencySpace<T>,
+{
+    #[serde(flatten)]
+    point: S,
+    error: T,
+    #[serde(default)]
+    adjustment: Option<T>,
+}
+
+/// Describes a sample which was applied by [`NetworkCoordinate::try_update`].
+///
+/// These are meant to be exported as metrics, e.g. a peer which keeps producing a high
+/// `relative_error` is likely measured badly, and a growing `displacement` hints at divergence.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct UpdateOutcome<T = f64> {
+    /// `es` from the paper: the relative error of this sample.
+    pub relative_error: T,
+    /// `w` from the paper: the weight which balanced local and remote error.
+    pub weight: T,
+    /// The local error estimate before the update.
+    pub previous_error: T,
+    /// The local error estimate after the update.
+    pub error: T,
+    /// How far the coordinate moved, in milliseconds. See [`LatencySpace::displacement`].
+    pub displacement: T,
+    /// Whether the update had to pick a random direction, e.g. because both coordinates were in
+    /// the same spot.
+    pub randomized: bool,
+}
+
+/// An estimated RTT between two coordinates, along with how far off it might be.
+///
+/// Both coordinates' error estimates are combined into `e = √(ei² + ej²)`, and the RTT is expected
+/// to be in `estimate × [1 − e, 1 + e]`. Coordinates which haven't converged yet have a large error,
+/// so their bounds are wide and their confidence is close to zero.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct RttEstimate<T = f64> {
+    /// The estimated RTT, same as [`NetworkCoordinate::estimated_rtt`].
+    pub estimate: Duration,
+    /// The lower bound of the RTT, never less than zero.
+    pub lower: Duration,
+    /// The upper bound of the RTT.
+    pub upper: Duration,
+    /// How much the estimate can be trusted, from `0` (not at all) to `1` (both coordinates have
+    /// no error). Computed as `1 / (1 + e)`.
+    pub confidence: T,
+}
+
+/// Wraps an RNG to record whether anything was drawn from it, which is how an update notices
+/// that a space fell back to a random direction.
+struct TrackingRng<'a, R>
+where
+    R: RngCore + ?Sized,
+{
+    inner: &'a mut R,
+    used: bool,
+}
+
+/// The terms of eqs. (1), (2) and (4) from the paper for a single validated sample, shared by
+/// single and batch updates.
+struct Sample<T> {
+    /// the sample weight `w`
+    weight: T,
+    /// the measured minus the estimated RTT, in milliseconds
+    error: T,
+    /// the relative error `es`
+    relative_error: T,
+    /// the timestep `δ`
+    delta: T,
 }
 
 // type aliases for convenience
@@ -62,22 +167,26 @@ pub struct NetworkCoordinate<const N: usize> {
 /// A 2D [`NetworkCoordinate`]. Includes a 2D Euclidean position and a height.
 ///
 /// This type alias is just for convenience. It's functionally equivalent to
-/// `NetworkCoordinate<2>`. For more information, see [`NetworkCoordinate`].
+/// `NetworkCoordinate<2, T>`. For more information, see [`NetworkCoordinate`].
 #[allow(clippy::module_name_repetitions)]
-pub type NetworkCoordinate2D = NetworkCoordinate<2>;
+pub type NetworkCoordinate2D<T = f64> = NetworkCoordinate<2, T>;
 
 /// A 3D [`NetworkCoordinate`]. Includes a 3D Euclidean position and a height.
 ///
 /// This type alias is just for convenience. It's functionally equivalent to
-/// `NetworkCoordinate<3>`. For more information, see [`NetworkCoordinate`].
+/// `NetworkCoordinate<3, T>`. For more information, see [`NetworkCoordinate`].
 #[allow(clippy::module_name_repetitions)]
-pub type NetworkCoordinate3D = NetworkCoordinate<3>;
+pub type NetworkCoordinate3D<T = f64> = NetworkCoordinate<3, T>;
 
 //
 // **** Implementations ****
 //
 
-impl<const N: usize> NetworkCoordinate<N> {
+impl<const N: usize, T, S> NetworkCoordinate<N, T, S>
+where
+    T: Real,
+    S: LatencySpace<T>,
+{
     /// Creates a new random [`NetworkCoordinate`]
     ///
     /// # Example
@@ -92,14 +201,94 @@ impl<const N: usize> NetworkCoordinate<N> {
     /// println!("Our new NC is: {:#?}", a);
     /// ```
     #[must_use]
+    #[cfg(feature = "std")]
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Creates a new random [`NetworkCoordinate`] whose initial error comes from `config`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vivaldi_nc::{NetworkCoordinate, VivaldiConfig};
+    ///
+    /// let config = VivaldiConfig::builder().default_error(50.0).build().unwrap();
+    /// let a: NetworkCoordinate<3> = NetworkCoordinate::new_with_config(&config);
+    /// assert!((a.error() - 50.0).abs() < 1e-6);
+    /// ```
+    #[must_use]
+    #[cfg(feature = "std")]
+    pub fn new_with_config(config: &VivaldiConfig<T>) -> Self {
+        Self {
+            point: S::random(&mut thread_rng()),
+            error: config.default_error(),
+            adjustment: None,
+            adjustment_samples: Vec::new(),
+            adjustment_index: 0,
+        }
+    }
+
+    /// Creates a new random [`NetworkCoordinate`] whose position is drawn from `rng`, so a seeded
+    /// `rng` always gives the same coordinate.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rand::{rngs::StdRng, SeedableRng};
+    /// use vivaldi_nc::NetworkCoordinate;
+    ///
+    /// let a: NetworkCoordinate<3> = NetworkCoordinate::new_with_rng(&mut StdRng::seed_from_u64(7));
+    /// let b: NetworkCoordinate<3> = NetworkCoordinate::new_with_rng(&mut StdRng::seed_from_u64(7));
+    /// assert_eq!(a.position(), b.position());
+    /// ```
+    #[must_use]
+    pub fn new_with_rng<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::with_point(S::random(rng))
+    }
+
+    /// Creates a new [`NetworkCoordinate`] at `point`, with the default initial error.
+    ///
+    /// Useful for spaces with their own parameters, like the curvature of a
+    /// [`HyperbolicVector`](crate::HyperbolicVector), which [`NetworkCoordinate::new`] can only
+    /// create with their defaults.
+    ///
+    /// # Errors
+    ///
+    /// - [`CoordinateError::InvalidPoint`] if `point` isn't valid in its space, see
+    ///   [`LatencySpace::is_valid`]
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vivaldi_nc::{HyperbolicVector, NetworkCoordinate};
+    ///
+    /// let point = HyperbolicVector::<3>::new([1.0, 0.0, 0.0], 1e-3).unwrap();
+    /// let a: NetworkCoordinate<3, f64, HyperbolicVector<3>> =
+    ///     NetworkCoordinate::from_point(point).unwrap();
+    /// ```
+    pub fn from_point(point: S) -> Result<Self, CoordinateError> {
+        if !point.is_valid() {
+            return Err(CoordinateError::InvalidPoint);
+        }
+        Ok(Self::with_point(point))
+    }
+
+    /// Creates a new [`NetworkCoordinate`] at a point which is known to be valid.
+    fn with_point(point: S) -> Self {
+        Self {
+            point,
+            error: VivaldiConfig::default().default_error(),
+            adjustment: None,
+            adjustment_samples: Vec::new(),
+            adjustment_index: 0,
+        }
+    }
+
     /// Given another Vivaldi [`NetworkCoordinate`], estimate the round trip time (ie ping) between them.
     ///
-    /// This is done by computing the height vector distance between between the two coordinates.
-    /// Vivaldi uses this distance as a representation of estimated round trip time.
+    /// The estimate is the distance between the two coordinates in their [`LatencySpace`], plus
+    /// their adjustment terms (if any) as long as the sum stays positive.
     ///
     /// # Parameters
     ///
@@ -107,11 +296,15 @@ impl<const N: usize> NetworkCoordinate<N> {
     ///
     /// # Returns
     ///
-    /// - the estimated round trip time as a `Duration`
+    /// - the estimated round trip time as a `Duration`, saturated like
+    ///   [`NetworkCoordinate::saturating_estimated_rtt`] if it doesn't fit (see
+    ///   [`NetworkCoordinate::try_estimated_rtt`] to detect that)
     ///
     /// # Example
     ///
     /// ```
+    /// # #[cfg(feature = "std")]
+    /// # fn main() {
     /// use vivaldi_nc::NetworkCoordinate;
     ///
     /// // create some 2-dimensional NCs for the sake of this example. These will just be random
@@ -121,23 +314,120 @@ impl<const N: usize> NetworkCoordinate<N> {
     ///
     /// // get the estimated RTT, convert to milliseconds, and print
     /// println!("Estimated RTT: {}", a.estimated_rtt(&b).as_millis());
+    /// # }
+    /// # #[cfg(not(feature = "std"))]
+    /// # fn main() {}
     /// ```
     ///
     #[must_use]
     pub fn estimated_rtt(&self, rhs: &Self) -> Duration {
-        // estimated rss is euclidean distance between the two plus the sum of the heights
-        cfg_if::cfg_if! {
-            if #[cfg(feature = "f32")] {
-                Duration::from_secs_f32((self.heightvec - rhs.heightvec).len() / 1000.0)
-            } else {
-                Duration::from_secs_f64((self.heightvec - rhs.heightvec).len() / 1000.0)
-            }
+        self.saturating_estimated_rtt(rhs)
+    }
+
+    /// Same as [`NetworkCoordinate::estimated_rtt`], but returns `None` instead of saturating when
+    /// the estimate is NaN, negative, or too large for a `Duration`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vivaldi_nc::NetworkCoordinate;
+    ///
+    /// let a = NetworkCoordinate::<2>::from_parts([0.0, 0.0], 0.0, 1.0).unwrap();
+    /// let b = NetworkCoordinate::<2>::from_parts([1e300, 0.0], 0.0, 1.0).unwrap();
+    /// assert_eq!(a.try_estimated_rtt(&b), None);
+    /// ```
+    #[must_use]
+    pub fn try_estimated_rtt(&self, rhs: &Self) -> Option<Duration> {
+        let secs = self.estimated_rtt_ms(rhs).as_f64() / 1000.0;
+        if (0.0..Duration::MAX.as_secs_f64()).contains(&secs) {
+            Some(Duration::from_secs_f64(secs))
+        } else {
+            None
+        }
+    }
+
+    /// Same as [`NetworkCoordinate::estimated_rtt`], but spells out what happens to estimates
+    /// which don't fit a `Duration`: too large ones become `Duration::MAX`, and NaN or negative
+    /// ones become zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::time::Duration;
+    /// use vivaldi_nc::NetworkCoordinate;
+    ///
+    /// let a = NetworkCoordinate::<2>::from_parts([0.0, 0.0], 0.0, 1.0).unwrap();
+    /// let b = NetworkCoordinate::<2>::from_parts([1e300, 0.0], 0.0, 1.0).unwrap();
+    /// assert_eq!(a.saturating_estimated_rtt(&b), Duration::MAX);
+    /// ```
+    #[must_use]
+    pub fn saturating_estimated_rtt(&self, rhs: &Self) -> Duration {
+        saturating_duration(self.estimated_rtt_ms(rhs))
+    }
+
+    /// Same as [`NetworkCoordinate::estimated_rtt`], but also bounds the estimate using the error
+    /// of both coordinates. See [`RttEstimate`] for how they're combined.
+    ///
+    /// # Parameters
+    ///
+    /// - `rhs`: the other coordinate
+    ///
+    /// # Returns
+    ///
+    /// - the estimated round trip time with its bounds and confidence. Bounds which are too
+    ///   large for a `Duration` are clamped to `Duration::MAX`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "std")]
+    /// # fn main() {
+    /// use vivaldi_nc::NetworkCoordinate;
+    ///
+    /// let a: NetworkCoordinate<2> = NetworkCoordinate::new();
+    /// let b: NetworkCoordinate<2> = NetworkCoordinate::new();
+    ///
+    /// let rtt = a.estimated_rtt_with_bounds(&b);
+    /// assert!(rtt.lower <= rtt.estimate && rtt.estimate <= rtt.upper);
+    ///
+    /// // fresh coordinates haven't learned anything yet
+    /// assert!(rtt.confidence < 0.01);
+    /// # }
+    /// # #[cfg(not(feature = "std"))]
+    /// # fn main() {}
+    /// ```
+    #[must_use]
+    pub fn estimated_rtt_with_bounds(&self, rhs: &Self) -> RttEstimate<T> {
+        let rtt_ms = self.estimated_rtt_ms(rhs);
+        let e = self.error.hypot(rhs.error);
+        RttEstimate {
+            estimate: saturating_duration(rtt_ms),
+            lower: saturating_duration(rtt_ms * (T::one() - e).max(T::zero())),
+            upper: saturating_duration(rtt_ms * (T::one() + e)),
+            confidence: (T::one() + e).recip(),
+        }
+    }
+
+    /// The estimated RTT in milliseconds: the distance between the two, e.g. euclidean distance
+    /// plus the sum of the heights for a `HeightVector`, plus the adjustments, unless that would
+    /// make it negative (same as Serf).
+    fn estimated_rtt_ms(&self, rhs: &Self) -> T {
+        let distance = self.point.distance(&rhs.point);
+        let adjusted =
+            distance + self.adjustment.unwrap_or_default() + rhs.adjustment.unwrap_or_default();
+        if adjusted > T::zero() {
+            adjusted
+        } else {
+            distance
         }
     }
 
     /// Given another Vivaldi [`NetworkCoordinate`], adjust our coordinateto better represent the actual round
     /// trip time (aka distance) between us.
     ///
+    /// Samples which can't be used (e.g. a zero `rtt`, or a corrupted `rhs`) are ignored and leave
+    /// `self` untouched. Use [`NetworkCoordinate::try_update`] to find out why a sample was rejected.
+    ///
     /// # Parameters
     ///
     /// - `rhs`: the other coordinate
@@ -145,7 +435,8 @@ impl<const N: usize> NetworkCoordinate<N> {
     ///
     /// # Returns
     ///
-    /// - a reference to `self`
+    /// - a reference to `self`, use [`NetworkCoordinate::try_update`] to get an [`UpdateOutcome`]
+    ///   describing what happened instead
     ///
     /// # Example
     ///
@@ -192,24 +483,226 @@ impl<const N: usize> NetworkCoordinate<N> {
     ///     xi = xi + δ × (rtt − ‖xi − xj ‖) × u(xi − xj)
     /// ```
     ///
+    #[cfg(feature = "std")]
     pub fn update(&mut self, rhs: &Self, rtt: Duration) -> &Self {
-        // convert Durations into FloatType as fractional milliseconds for convenience
-        cfg_if::cfg_if! {
-            if #[cfg(feature = "f32")] {
-                let rtt_ms = rtt.as_secs_f32() * 1000.0;
-                let rtt_estimated_ms = self.estimated_rtt(rhs).as_secs_f32() * 1000.0;
-            } else {
-                let rtt_ms = rtt.as_secs_f64() * 1000.0;
-                let rtt_estimated_ms = self.estimated_rtt(rhs).as_secs_f64() * 1000.0;
-            }
+        self.update_with_config(rhs, rtt, &VivaldiConfig::default())
+    }
+
+    /// Same as [`NetworkCoordinate::update`], but uses the tuning parameters from `config` instead
+    /// of the defaults.
+    ///
+    /// # Parameters
+    ///
+    /// - `rhs`: the other coordinate
+    /// - `rtt`: the measured round trip time between `self` and `rhs`
+    /// - `config`: the Vivaldi tuning parameters
+    ///
+    /// # Returns
+    ///
+    /// - a reference to `self`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::time::Duration;
+    /// use vivaldi_nc::{NetworkCoordinate, VivaldiConfig};
+    ///
+    /// let config = VivaldiConfig::builder().c_delta(0.05).build().unwrap();
+    /// let mut local: NetworkCoordinate<2> = NetworkCoordinate::new_with_config(&config);
+    /// let remote: NetworkCoordinate<2> = NetworkCoordinate::new_with_config(&config);
+    ///
+    /// local.update_with_config(&remote, Duration::from_millis(100), &config);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn update_with_config(
+        &mut self,
+        rhs: &Self,
+        rtt: Duration,
+        config: &VivaldiConfig<T>,
+    ) -> &Self {
+        // an invalid sample leaves `self` untouched, callers who care use `try_update()`
+        let _ = self.try_update_with_config(rhs, rtt, config);
+
+        // return reference to updated self
+        self
+    }
+
+    /// Fallible version of [`NetworkCoordinate::update`].
+    ///
+    /// The sample is validated before it is applied. If it's rejected, `self` is left untouched
+    /// and the reason is returned, so callers can count or log bad samples from remote peers.
+    ///
+    /// # Parameters
+    ///
+    /// - `rhs`: the other coordinate
+    /// - `rtt`: the measured round trip time between `self` and `rhs`
+    ///
+    /// # Returns
+    ///
+    /// - an [`UpdateOutcome`] describing the applied sample
+    ///
+    /// # Errors
+    ///
+    /// - [`UpdateError::RttTooSmall`] if `rtt` is zero or too small to compute a relative error
+    /// - [`UpdateError::InvalidRemoteCoordinate`] if `rhs` has a NaN, infinite, or negative
+    ///   component
+    /// - [`UpdateError::InvalidRemoteError`] if the error estimate of `rhs` isn't a positive,
+    ///   finite number
+    /// - [`UpdateError::NumericOverflow`] if the update would produce a NaN or infinite value
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::time::Duration;
+    /// use vivaldi_nc::{NetworkCoordinate, UpdateError};
+    ///
+    /// let mut local: NetworkCoordinate<2> = NetworkCoordinate::new();
+    /// let remote: NetworkCoordinate<2> = NetworkCoordinate::new();
+    ///
+    /// // a zero RTT can't be used to compute a relative error
+    /// assert_eq!(local.try_update(&remote, Duration::ZERO), Err(UpdateError::RttTooSmall));
+    ///
+    /// // a good sample is applied
+    /// let outcome = local.try_update(&remote, Duration::from_millis(100)).unwrap();
+    /// assert!((outcome.error - local.error()).abs() < 1e-6);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn try_update(
+        &mut self,
+        rhs: &Self,
+        rtt: Duration,
+    ) -> Result<UpdateOutcome<T>, UpdateError> {
+        self.try_update_with_config(rhs, rtt, &VivaldiConfig::default())
+    }
+
+    /// Same as [`NetworkCoordinate::try_update`], but uses the tuning parameters from `config`
+    /// instead of the defaults.
+    ///
+    /// # Errors
+    ///
+    /// See [`NetworkCoordinate::try_update`].
+    #[cfg(feature = "std")]
+    pub fn try_update_with_config(
+        &mut self,
+        rhs: &Self,
+        rtt: Duration,
+        config: &VivaldiConfig<T>,
+    ) -> Result<UpdateOutcome<T>, UpdateError> {
+        self.try_update_with_rng(rhs, rtt, config, &mut thread_rng())
+    }
+
+    /// Same as [`NetworkCoordinate::update_with_config`], but draws any randomness the update
+    /// needs (e.g. a direction to move in when both coordinates are in the same spot) from `rng`.
+    ///
+    /// Together with [`NetworkCoordinate::new_with_rng`], a seeded `rng` makes a whole simulation
+    /// reproducible.
+    ///
+    /// # Returns
+    ///
+    /// - a reference to `self`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::time::Duration;
+    /// use rand::{rngs::StdRng, SeedableRng};
+    /// use vivaldi_nc::{NetworkCoordinate, VivaldiConfig};
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let config = VivaldiConfig::default();
+    /// let mut local: NetworkCoordinate<2> = NetworkCoordinate::new_with_rng(&mut rng);
+    /// let remote: NetworkCoordinate<2> = NetworkCoordinate::new_with_rng(&mut rng);
+    ///
+    /// local.update_with_rng(&remote, Duration::from_millis(100), &config, &mut rng);
+    /// ```
+    pub fn update_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        rhs: &Self,
+        rtt: Duration,
+        config: &VivaldiConfig<T>,
+        rng: &mut R,
+    ) -> &Self {
+        // an invalid sample leaves `self` untouched, callers who care use `try_update_with_rng()`
+        let _ = self.try_update_with_rng(rhs, rtt, config, rng);
+        self
+    }
+
+    /// Fallible version of [`NetworkCoordinate::update_with_rng`].
+    ///
+    /// # Errors
+    ///
+    /// See [`NetworkCoordinate::try_update`].
+    pub fn try_update_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        rhs: &Self,
+        rtt: Duration,
+        config: &VivaldiConfig<T>,
+        rng: &mut R,
+    ) -> Result<UpdateOutcome<T>, UpdateError> {
+        // convert Durations into T as fractional milliseconds for convenience
+        let rtt_ms = T::from_f64(rtt.as_secs_f64() * 1000.0);
+        let sample = self.sample(rhs, rtt_ms, config)?;
+        let new_error = self.updated_error(sample.relative_error, sample.weight, config);
+
+        // Update local coordinates. (4)
+        // xi = xi + δ × (rtt − ‖xi − xj ‖) × u(xi − xj)
+        let scale = sample.delta * sample.error;
+        if !(new_error.is_finite() && scale.is_finite()) {
+            return Err(UpdateError::NumericOverflow);
+        }
+        // NOTE: `scale` is negative when the nodes are closer than estimated, which shrinks the
+        //       height of a `HeightVector` too, so let the space keep the point valid (and e.g.
+        //       apply gravity)
+        let mut rng = TrackingRng {
+            inner: rng,
+            used: false,
+        };
+        let new_point = self
+            .point
+            .moved_away(&rhs.point, scale, &mut rng)
+            .constrained(config);
+        if !new_point.is_valid() {
+            return Err(UpdateError::NumericOverflow);
+        }
+
+        // everything checks out, commit the update
+        let outcome = UpdateOutcome {
+            relative_error: sample.relative_error,
+            weight: sample.weight,
+            previous_error: self.error,
+            error: new_error,
+            displacement: new_point.displacement(&self.point),
+            randomized: rng.used,
+        };
+        self.point = new_point;
+        self.error = new_error;
+        self.update_adjustment(rtt_ms - new_point.distance(&rhs.point), config);
+
+        Ok(outcome)
+    }
+
+    /// Validates a sample and computes its terms of the update, without changing `self`.
+    fn sample(
+        &self,
+        rhs: &Self,
+        rtt_ms: T,
+        config: &VivaldiConfig<T>,
+    ) -> Result<Sample<T>, UpdateError> {
+        // reject remote values we can't possibly use before doing any math with them
+        if !rhs.point.is_valid() || rhs.adjustment.map_or(false, |a| !a.is_finite()) {
+            return Err(UpdateError::InvalidRemoteCoordinate);
+        }
+        if !(rhs.error.is_finite() && rhs.error > T::zero()) {
+            return Err(UpdateError::InvalidRemoteError);
         }
 
-        // rtt needs to be positive
-        if rtt_ms < 0.0 {
-            // Note: `rtt` is guaranteed to be positive because `Duration` enforces it.
-            //       If this panics, something changed where `Duration` now allows for negative
-            //       values.
-            unreachable!();
+        // rtt needs to be positive, and a relative error needs to be computable from it
+        if rtt_ms <= T::zero() {
+            return Err(UpdateError::RttTooSmall);
+        }
+        let rtt_estimated_ms = self.point.distance(&rhs.point);
+        if !rtt_estimated_ms.is_finite() {
+            return Err(UpdateError::NumericOverflow);
         }
 
         // Sample weight balances local and remote error. (1)
@@ -220,41 +713,283 @@ impl<const N: usize> NetworkCoordinate<N> {
         // es = ∣∣∣‖xi − xj‖ − rtt∣∣∣/rtt
         let error = rtt_ms - rtt_estimated_ms;
         let es = error.abs() / rtt_ms;
+        if !es.is_finite() {
+            return Err(UpdateError::RttTooSmall);
+        }
+
+        // The timestep for (4).
+        // δ = cc × w (or one of the other rules from the paper's evaluation)
+        let delta = config.update_rule().delta(config.c_delta(), self.error, w);
 
+        Ok(Sample {
+            weight: w,
+            error,
+            relative_error: es,
+            delta,
+        })
+    }
+
+    /// The local error after a sample with relative error `es` and weight `w`, which may not be
+    /// finite if the update overflowed.
+    fn updated_error(&self, es: T, w: T, config: &VivaldiConfig<T>) -> T {
         // Update weighted moving average of local error. (3)
         // ei = es × ce × w + ei × (1 − ce × w)
         // self.error = (es * C_ERROR * w + self.error * (1.0 - C_ERROR * w)).max(MIN_ERROR);
         // NOTE: using `mul_add()` which is a little safer (avoid overflows)
-        self.error = (es * C_ERROR)
-            .mul_add(w, self.error * C_ERROR.mul_add(-w, 1.0))
-            .max(MIN_ERROR);
+        let c_error = config.c_error();
+        (es * c_error)
+            .mul_add(w, self.error * c_error.mul_add(-w, T::one()))
+            .max(config.min_error())
+    }
 
-        // Update local coordinates. (4)
-        // δ = cc × w
-        let delta = C_DELTA * w;
-        // xi = xi + δ × (rtt − ‖xi − xj ‖) × u(xi − xj)
-        self.heightvec =
-            self.heightvec + (self.heightvec - rhs.heightvec).normalized() * delta * error;
+    /// getter for error value - useful for consumers to understand the estimated accuracty of this
+    /// `NetworkCoordinate`
+    #[must_use]
+    pub const fn error(&self) -> T {
+        self.error
+    }
 
-        // if we ended up with an invalid coordinate, return a new random coordinate with default
-        // error
-        if self.heightvec.is_invalid() {
-            *self = Self::new();
+    /// Overwrites the error estimate, e.g. with one computed by a centralized solver. `error` must
+    /// be finite and greater than zero.
+    #[cfg(feature = "std")]
+    pub(crate) fn set_error(&mut self, error: T) {
+        debug_assert!(error.is_finite() && error > T::zero());
+        self.error = error;
+    }
 
-            // We should never get here because the call to `normalized()` above should catch any
-            // invalid `heightvec`
-            unreachable!();
+    /// getter for the point in the coordinate's [`LatencySpace`], e.g. its [`HeightVector`]
+    #[must_use]
+    pub const fn point(&self) -> &S {
+        &self.point
+    }
+
+    /// getter for the adjustment term in milliseconds, `None` unless adjustments are enabled with
+    /// [`VivaldiConfigBuilder::adjustment_window`](crate::VivaldiConfigBuilder::adjustment_window)
+    #[must_use]
+    pub const fn adjustment(&self) -> Option<T> {
+        self.adjustment
+    }
+
+    /// Records the residual (measured minus estimated RTT) of the latest sample and recomputes the
+    /// adjustment term from the window of recent residuals, like Serf does.
+    #[allow(clippy::cast_precision_loss)]
+    fn update_adjustment(&mut self, residual: T, config: &VivaldiConfig<T>) {
+        let window = config.adjustment_window();
+        if window == 0 {
+            self.adjustment = None;
+            self.adjustment_samples.clear();
+            self.adjustment_index = 0;
+            return;
         }
 
-        // return reference to updated self
-        self
+        // (re)size the ring buffer in case the window changed
+        self.adjustment_samples.resize(window, T::zero());
+        self.adjustment_index %= window;
+
+        self.adjustment_samples[self.adjustment_index] = residual;
+        self.adjustment_index = (self.adjustment_index + 1) % window;
+
+        // each side of a pair contributes half of the adjustment
+        let sum = self
+            .adjustment_samples
+            .iter()
+            .fold(T::zero(), |sum, &residual| sum + residual);
+        self.adjustment = Some(sum / T::from_f64(2.0 * window as f64));
     }
+}
 
-    /// getter for error value - useful for consumers to understand the estimated accuracty of this
-    /// `NetworkCoordinate`
+impl<const N: usize, T> NetworkCoordinate<N, T>
+where
+    T: Real,
+{
+    /// Creates a [`NetworkCoordinate`] from its components, e.g. when loading it from storage or
+    /// building test fixtures.
+    ///
+    /// The components are validated just like a deserialized coordinate, with no fallback to a
+    /// random position.
+    ///
+    /// # Errors
+    ///
+    /// - [`CoordinateError::NonFinitePosition`] if a component of `position` is NaN or infinite
+    /// - [`CoordinateError::InvalidHeight`] if `height` is negative, NaN or infinite
+    /// - [`CoordinateError::InvalidError`] if `error` isn't finite and greater than zero
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vivaldi_nc::NetworkCoordinate;
+    ///
+    /// let a = NetworkCoordinate::<2>::from_parts([1.5, 0.5], 0.1, 1.0).unwrap();
+    /// assert_eq!(a.position(), [1.5, 0.5]);
+    /// assert!((a.height() - 0.1).abs() < 1e-9);
+    ///
+    /// assert!(NetworkCoordinate::<2>::from_parts([1.5, 0.5], -0.1, 1.0).is_err());
+    /// ```
+    pub fn from_parts(position: [T; N], height: T, error: T) -> Result<Self, CoordinateError> {
+        let point = HeightVector::new(position, height)?;
+        if !(error.is_finite() && error > T::zero()) {
+            return Err(CoordinateError::InvalidError);
+        }
+        Ok(Self {
+            point,
+            error,
+            adjustment: None,
+            adjustment_samples: Vec::new(),
+            adjustment_index: 0,
+        })
+    }
+
+    /// getter for the Euclidean position of the coordinate, in milliseconds
     #[must_use]
-    pub const fn error(&self) -> FloatType {
-        self.error
+    pub const fn position(&self) -> [T; N] {
+        self.point.position()
+    }
+
+    /// getter for the height of the coordinate (the estimated latency to the network core), in
+    /// milliseconds
+    #[must_use]
+    pub const fn height(&self) -> T {
+        self.point.height()
+    }
+
+    /// Updates the coordinate with a whole round of RTT samples at once, so the result doesn't
+    /// depend on the order they were measured in.
+    ///
+    /// Like the spring relaxation described in the paper, the forces from all samples are summed
+    /// and applied in a single move. Each force is computed just like in
+    /// [`NetworkCoordinate::update`], and the timestep is divided by the number of samples so a
+    /// round with many peers doesn't overshoot. The error estimate is updated once, with the
+    /// weighted mean relative error of the round.
+    ///
+    /// Only available for the default height vector model.
+    ///
+    /// # Parameters
+    ///
+    /// - `samples`: the remote coordinates and the RTTs measured to them
+    ///
+    /// # Returns
+    ///
+    /// - a reference to `self`, which is left untouched if any sample is invalid
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::time::Duration;
+    /// use vivaldi_nc::NetworkCoordinate;
+    ///
+    /// let mut local: NetworkCoordinate<2> = NetworkCoordinate::new();
+    /// let a: NetworkCoordinate<2> = NetworkCoordinate::new();
+    /// let b: NetworkCoordinate<2> = NetworkCoordinate::new();
+    ///
+    /// local.update_batch([(&a, Duration::from_millis(40)), (&b, Duration::from_millis(90))]);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn update_batch<'a, I>(&mut self, samples: I) -> &Self
+    where
+        I: IntoIterator<Item = (&'a Self, Duration)>,
+    {
+        self.update_batch_with_config(samples, &VivaldiConfig::default())
+    }
+
+    /// Same as [`NetworkCoordinate::update_batch`], but uses the tuning parameters from `config`
+    /// instead of the defaults.
+    #[cfg(feature = "std")]
+    pub fn update_batch_with_config<'a, I>(
+        &mut self,
+        samples: I,
+        config: &VivaldiConfig<T>,
+    ) -> &Self
+    where
+        I: IntoIterator<Item = (&'a Self, Duration)>,
+    {
+        // an invalid batch leaves `self` untouched, callers who care use
+        // `try_update_batch_with_rng()`
+        let _ = self.try_update_batch_with_rng(samples, config, &mut thread_rng());
+        self
+    }
+
+    /// Fallible version of [`NetworkCoordinate::update_batch_with_config`], which draws any
+    /// randomness it needs from `rng`.
+    ///
+    /// The whole batch is validated before it is applied. If any sample is rejected, `self` is
+    /// left untouched.
+    ///
+    /// # Returns
+    ///
+    /// - an [`UpdateOutcome`] describing the round, where `relative_error` is the weighted mean
+    ///   of the samples' relative errors and `weight` is the mean of their weights
+    ///
+    /// # Errors
+    ///
+    /// - [`UpdateError::EmptyBatch`] if there are no samples
+    /// - any error [`NetworkCoordinate::try_update`] returns for a single sample
+    pub fn try_update_batch_with_rng<'a, I, R>(
+        &mut self,
+        samples: I,
+        config: &VivaldiConfig<T>,
+        rng: &mut R,
+    ) -> Result<UpdateOutcome<T>, UpdateError>
+    where
+        I: IntoIterator<Item = (&'a Self, Duration)>,
+        R: Rng + ?Sized,
+    {
+        let samples: Vec<(&Self, T)> = samples
+            .into_iter()
+            .map(|(rhs, rtt)| (rhs, T::from_f64(rtt.as_secs_f64() * 1000.0)))
+            .collect();
+        if samples.is_empty() {
+            return Err(UpdateError::EmptyBatch);
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let n = T::from_f64(samples.len() as f64);
+
+        let mut rng = TrackingRng {
+            inner: rng,
+            used: false,
+        };
+        let mut force: Option<HeightVector<N, T>> = None;
+        let (mut sum_w, mut sum_w_es) = (T::zero(), T::zero());
+        for &(rhs, rtt_ms) in &samples {
+            // (1) and (2) from the paper, per sample, with the same validation as a single sample
+            let sample = self.sample(rhs, rtt_ms, config)?;
+            sum_w = sum_w + sample.weight;
+            sum_w_es = sample.weight.mul_add(sample.relative_error, sum_w_es);
+
+            // the spring force of this sample, with the timestep shared by the whole batch
+            let f =
+                (self.point - rhs.point).normalized(&mut rng) * (sample.delta * sample.error / n);
+            force = Some(force.map_or(f, |sum| sum + f));
+        }
+
+        // (3) from the paper, once for the whole batch
+        let w = sum_w / n;
+        let es = sum_w_es / sum_w;
+        let new_error = self.updated_error(es, w, config);
+
+        // (4) from the paper, with the summed force
+        let new_point = force
+            .map_or(self.point, |f| self.point + f)
+            .constrained(config);
+        if !(new_error.is_finite() && new_point.is_valid()) {
+            return Err(UpdateError::NumericOverflow);
+        }
+
+        // everything checks out, commit the update
+        let outcome = UpdateOutcome {
+            relative_error: es,
+            weight: w,
+            previous_error: self.error,
+            error: new_error,
+            displacement: new_point.displacement(&self.point),
+            randomized: rng.used,
+        };
+        self.point = new_point;
+        self.error = new_error;
+        for &(rhs, rtt_ms) in &samples {
+            self.update_adjustment(rtt_ms - new_point.distance(&rhs.point), config);
+        }
+
+        Ok(outcome)
     }
 }
 
@@ -262,13 +997,82 @@ impl<const N: usize> NetworkCoordinate<N> {
 // **** Trait Implementations ****
 //
 
-impl<const N: usize> Default for NetworkCoordinate<N> {
-    /// A default `NetworkCoordinate` has a random position and `DEFAULT_ERROR`
-    fn default() -> Self {
-        Self {
-            heightvec: HeightVector::<N>::random(),
-            error: DEFAULT_ERROR,
+impl<R> RngCore for TrackingRng<'_, R>
+where
+    R: RngCore + ?Sized,
+{
+    fn next_u32(&mut self) -> u32 {
+        self.used = true;
+        self.inner.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.used = true;
+        self.inner.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.used = true;
+        self.inner.fill_bytes(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.used = true;
+        self.inner.try_fill_bytes(dest)
+    }
+}
+
+impl<const N: usize, T, S> TryFrom<NetworkCoordinateRepr<N, T, S>> for NetworkCoordinate<N, T, S>
+where
+    T: Real,
+    S: LatencySpace<T>,
+{
+    type Error = CoordinateError;
+
+    /// Validate a deserialized coordinate. The point validates itself.
+    fn try_from(value: NetworkCoordinateRepr<N, T, S>) -> Result<Self, Self::Error> {
+        if !(value.error.is_finite() && value.error > T::zero()) {
+            return Err(CoordinateError::InvalidError);
         }
+        if value.adjustment.map_or(false, |a| !a.is_finite()) {
+            return Err(CoordinateError::InvalidAdjustment);
+        }
+        Ok(Self {
+            point: value.point,
+            error: value.error,
+            adjustment: value.adjustment,
+            adjustment_samples: Vec::new(),
+            adjustment_index: 0,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<const N: usize, T, S> Default for NetworkCoordinate<N, T, S>
+where
+    T: Real,
+    S: LatencySpace<T>,
+{
+    /// A default `NetworkCoordinate` has a random position and the default config's error
+    fn default() -> Self {
+        Self::new_with_config(&VivaldiConfig::default())
+    }
+}
+
+//
+// **** Functions ****
+//
+
+/// Converts fractional milliseconds into a `Duration`, clamping to `[0, Duration::MAX]` instead of
+/// panicking. NaN becomes zero.
+fn saturating_duration<T: Real>(ms: T) -> Duration {
+    let secs = ms.as_f64() / 1000.0;
+    if secs >= Duration::MAX.as_secs_f64() {
+        Duration::MAX
+    } else if secs > 0.0 {
+        Duration::from_secs_f64(secs)
+    } else {
+        Duration::ZERO
     }
 }
 
@@ -277,10 +1081,17 @@ impl<const N: usize> Default for NetworkCoordinate<N> {
 //
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "std")]
+    use core::mem::size_of;
+
     use assert_approx_eq::assert_approx_eq;
 
     use super::*;
+    #[cfg(feature = "std")]
+    use crate::config::UpdateRule;
+    use crate::spherical::SphericalVector;
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_convergence() {
         let mut a = NetworkCoordinate::<3>::new();
@@ -294,6 +1105,56 @@ mod tests {
         assert_approx_eq!(rtt.as_secs_f32() * 1000.0, 250.0, 1.0);
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_mixed_precision() {
+        // f32 and f64 coordinates side by side, exchanging positions over the wire
+        let mut a = NetworkCoordinate::<3, f32>::new();
+        let mut b = NetworkCoordinate::<3, f64>::new();
+        let t = Duration::from_millis(250);
+        (0..20).for_each(|_| {
+            let b32: NetworkCoordinate<3, f32> =
+                serde_json::from_str(&serde_json::to_string(&b).expect("serialize failed"))
+                    .expect("deserialize failed");
+            a.update(&b32, t);
+            let a64: NetworkCoordinate<3> =
+                serde_json::from_str(&serde_json::to_string(&a).expect("serialize failed"))
+                    .expect("deserialize failed");
+            b.update(&a64, t);
+        });
+        let b32: NetworkCoordinate<3, f32> =
+            serde_json::from_str(&serde_json::to_string(&b).expect("serialize failed"))
+                .expect("deserialize failed");
+        assert_approx_eq!(a.estimated_rtt(&b32).as_secs_f32() * 1000.0, 250.0, 1.0);
+        assert_eq!(
+            size_of::<HeightVector<3, f32>>() * 2,
+            size_of::<HeightVector<3, f64>>()
+        );
+    }
+
+    #[test]
+    fn test_seeded_rng() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let config = VivaldiConfig::default();
+        let run = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut a: NetworkCoordinate<3> = NetworkCoordinate::new_with_rng(&mut rng);
+            let mut b: NetworkCoordinate<3> = NetworkCoordinate::new_with_rng(&mut rng);
+            // coincident coordinates need a random direction to move apart
+            let c = b.clone();
+            b.update_with_rng(&c, Duration::from_millis(50), &config, &mut rng);
+            for ms in [100, 120, 90, 110] {
+                a.update_with_rng(&b, Duration::from_millis(ms), &config, &mut rng);
+                b.update_with_rng(&a, Duration::from_millis(ms), &config, &mut rng);
+            }
+            serde_json::to_string(&[a, b]).expect("serialization failed during test")
+        };
+        assert_eq!(run(3), run(3));
+        assert_ne!(run(3), run(4));
+    }
+
+    #[cfg(feature = "std")]
     #[test]
     fn test_mini_network() {
         // define a little network with these nodes:
@@ -367,7 +1228,7 @@ mod tests {
             serde_json::from_str(s).expect("deserialization failed during test");
 
         // make sure it's the right length and works like we expect a normal NC
-        assert_approx_eq!(a.heightvec.len(), 2.649_509, 0.001);
+        assert_approx_eq!(a.point.len(), 2.649_509, 0.001);
         assert_approx_eq!(a.error, 1.0);
         assert_eq!(a.estimated_rtt(&a).as_millis(), 0);
 
@@ -376,6 +1237,49 @@ mod tests {
         assert_eq!(t.as_ref().expect("serialization failed during test"), s);
     }
 
+    #[test]
+    fn test_deserialize_invalid() {
+        // negative height
+        let s = "{\"position\":[1.5,0.5,2.0],\"height\":-0.1,\"error\":1.0}";
+        let e = serde_json::from_str::<NetworkCoordinate<3>>(s)
+            .expect_err("invalid height accepted during test");
+        assert!(e.to_string().contains("height"));
+
+        // zero and negative error
+        let s = "{\"position\":[1.5,0.5,2.0],\"height\":0.1,\"error\":0.0}";
+        let e = serde_json::from_str::<NetworkCoordinate<3>>(s)
+            .expect_err("invalid error accepted during test");
+        assert!(e
+            .to_string()
+            .contains("error must be finite and greater than zero"));
+        let s = "{\"position\":[1.5,0.5,2.0],\"height\":0.1,\"error\":-1.0}";
+        assert!(serde_json::from_str::<NetworkCoordinate<3>>(s).is_err());
+
+        // non-numeric adjustment
+        let s = "{\"position\":[1.5,0.5,2.0],\"height\":0.1,\"error\":1.0,\"adjustment\":\"x\"}";
+        assert!(serde_json::from_str::<NetworkCoordinate<3>>(s).is_err());
+
+        // wrong dimensionality
+        let s = "{\"position\":[1.5,0.5],\"height\":0.1,\"error\":1.0}";
+        assert!(serde_json::from_str::<NetworkCoordinate<3>>(s).is_err());
+    }
+
+    #[test]
+    fn test_serde_model() {
+        // other models add a discriminator to the same message shape
+        let s =
+            "{\"model\":\"spherical\",\"position\":[0.0,0.0,80.0],\"radius\":80.0,\"error\":1.0}";
+        let a: NetworkCoordinate<3, f64, SphericalVector<3>> =
+            serde_json::from_str(s).expect("deserialization failed during test");
+        let t = serde_json::to_string(&a).expect("serialization failed during test");
+        assert_eq!(t, s);
+
+        // which receivers expecting another model reject
+        let e = serde_json::from_str::<NetworkCoordinate<3>>(s)
+            .expect_err("wrong model accepted during test");
+        assert!(e.to_string().contains("model"));
+    }
+
     #[test]
     fn test_estimated_rtt() {
         // start with JSON, deserialize it
@@ -390,6 +1294,378 @@ mod tests {
         assert_approx_eq!(estimate.as_secs_f32(), 0.080_099);
     }
 
+    #[test]
+    fn test_extreme_estimated_rtt() {
+        let a = NetworkCoordinate::<2>::from_parts([0.0, 0.0], 10.0, 1.0)
+            .expect("valid parts rejected during test");
+        let b = NetworkCoordinate::<2>::from_parts([30.0, 40.0], 10.0, 1.0)
+            .expect("valid parts rejected during test");
+        assert_eq!(a.try_estimated_rtt(&b), Some(Duration::from_millis(70)));
+        assert_eq!(a.saturating_estimated_rtt(&b), Duration::from_millis(70));
+
+        // too far apart for a `Duration`, and too far apart for a float
+        for x in [1e300, f64::MAX] {
+            let far = NetworkCoordinate::<2>::from_parts([-x, 0.0], 10.0, 1.0)
+                .expect("valid parts rejected during test");
+            let farther = NetworkCoordinate::<2>::from_parts([x, 0.0], 10.0, 1.0)
+                .expect("valid parts rejected during test");
+            assert_eq!(far.try_estimated_rtt(&farther), None);
+            assert_eq!(far.saturating_estimated_rtt(&farther), Duration::MAX);
+            assert_eq!(far.estimated_rtt(&farther), Duration::MAX);
+        }
+    }
+
+    #[test]
+    fn test_estimated_rtt_with_bounds() {
+        let a = NetworkCoordinate::<3>::from_parts([1.5, 0.5, 2.0], 25.0, 0.3)
+            .expect("valid parts rejected during test");
+        let b = NetworkCoordinate::<3>::from_parts([-1.5, -0.5, -2.0], 50.0, 0.4)
+            .expect("valid parts rejected during test");
+
+        // e = √(0.3² + 0.4²) = 0.5
+        let rtt = a.estimated_rtt_with_bounds(&b);
+        assert_eq!(rtt.estimate, a.estimated_rtt(&b));
+        assert_approx_eq!(rtt.lower.as_secs_f64(), 0.080_099 * 0.5, 1e-6);
+        assert_approx_eq!(rtt.upper.as_secs_f64(), 0.080_099 * 1.5, 1e-6);
+        assert_approx_eq!(rtt.confidence, 2.0 / 3.0);
+
+        // huge errors clamp the bounds instead of panicking
+        let c = NetworkCoordinate::<3>::from_parts([1.5, 0.5, 2.0], 25.0, 1e300)
+            .expect("valid parts rejected during test");
+        let rtt = c.estimated_rtt_with_bounds(&b);
+        assert_eq!(rtt.lower, Duration::ZERO);
+        assert_eq!(rtt.upper, Duration::MAX);
+        assert!(rtt.confidence < 1e-299);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_config() {
+        let config = VivaldiConfig::builder()
+            .c_error(0.5)
+            .default_error(10.0)
+            .build()
+            .expect("valid config rejected during test");
+        let mut a = NetworkCoordinate::<2>::new_with_config(&config);
+        let b = NetworkCoordinate::<2>::new_with_config(&config);
+        assert_approx_eq!(a.error(), 10.0);
+
+        // with equal errors w = 0.5, so the new error is es * 0.25 + 10.0 * 0.75
+        let rtt = Duration::from_millis(100);
+        let estimate = a.estimated_rtt(&b).as_secs_f64();
+        let es = estimate.mul_add(1000.0, -100.0).abs() / 100.0;
+        a.update_with_config(&b, rtt, &config);
+        assert_approx_eq!(a.error(), es.mul_add(0.25, 7.5), 0.001);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_try_update() {
+        let mut a = NetworkCoordinate::<3>::new();
+        let b = NetworkCoordinate::<3>::new();
+        let outcome = a
+            .try_update(&b, Duration::from_millis(100))
+            .expect("valid sample rejected during test");
+        assert_approx_eq!(outcome.weight, 0.5);
+        assert_approx_eq!(outcome.error, a.error());
+        assert!(outcome.relative_error > 0.0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_update_outcome() {
+        let mut a = NetworkCoordinate::<2>::from_parts([0.0, 0.0], 10.0, 1.0)
+            .expect("valid parts rejected during test");
+        let b = NetworkCoordinate::<2>::from_parts([30.0, 40.0], 10.0, 1.0)
+            .expect("valid parts rejected during test");
+
+        // estimated 70ms, measured 100ms, so `a` moves 0.25 × 0.5 × 30ms away from `b`
+        let outcome = a
+            .try_update(&b, Duration::from_millis(100))
+            .expect("valid sample rejected during test");
+        assert_approx_eq!(outcome.previous_error, 1.0);
+        assert_approx_eq!(outcome.error, a.error());
+        assert_approx_eq!(outcome.displacement, 3.75);
+        assert!(!outcome.randomized);
+
+        // a node in the same spot as its peer, with no height, has to pick a random direction
+        let mut a = NetworkCoordinate::<2>::from_parts([5.0, 5.0], 0.0, 1.0)
+            .expect("valid parts rejected during test");
+        let c = a.clone();
+        let outcome = a
+            .try_update(&c, Duration::from_millis(100))
+            .expect("valid sample rejected during test");
+        assert!(outcome.randomized);
+        assert!(outcome.displacement > 0.0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_update_batch() {
+        let peers = [
+            NetworkCoordinate::<2>::from_parts([0.0, 0.0], 5.0, 0.1),
+            NetworkCoordinate::<2>::from_parts([60.0, 0.0], 5.0, 0.1),
+            NetworkCoordinate::<2>::from_parts([0.0, 80.0], 5.0, 0.1),
+        ]
+        .map(|p| p.expect("valid parts rejected during test"));
+        let rtts = [65, 65, 65].map(Duration::from_millis);
+
+        // the order of the samples doesn't matter
+        let start = NetworkCoordinate::<2>::from_parts([20.0, 20.0], 5.0, 1.0)
+            .expect("valid parts rejected during test");
+        let (mut a, mut b) = (start.clone(), start);
+        let outcome = a
+            .try_update_batch_with_rng(
+                peers.iter().zip(rtts),
+                &VivaldiConfig::default(),
+                &mut thread_rng(),
+            )
+            .expect("valid batch rejected during test");
+        b.update_batch(peers.iter().zip(rtts).rev());
+        assert_approx_eq!(a.position()[0], b.position()[0]);
+        assert_approx_eq!(a.position()[1], b.position()[1]);
+        assert_approx_eq!(a.error(), b.error());
+        assert_approx_eq!(outcome.previous_error, 1.0);
+        assert!(outcome.displacement > 0.0);
+        assert!(!outcome.randomized);
+
+        // repeated rounds converge, to (30, 40) with a height of 10
+        (0..1000).for_each(|_| {
+            a.update_batch(peers.iter().zip(rtts));
+        });
+        for (peer, rtt) in peers.iter().zip(rtts) {
+            let estimate = a.estimated_rtt(peer).as_secs_f64();
+            assert_approx_eq!(estimate, rtt.as_secs_f64(), 0.001);
+        }
+        assert_approx_eq!(a.position()[0], 30.0, 1.0);
+        assert_approx_eq!(a.position()[1], 40.0, 1.0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_update_batch_invalid() {
+        let mut a = NetworkCoordinate::<2>::new();
+        let before = serde_json::to_string(&a).expect("serialization failed during test");
+        let config = VivaldiConfig::default();
+
+        let samples: [(&NetworkCoordinate<2>, Duration); 0] = [];
+        assert_eq!(
+            a.try_update_batch_with_rng(samples, &config, &mut thread_rng()),
+            Err(UpdateError::EmptyBatch)
+        );
+
+        // one bad sample rejects the whole batch
+        let b = NetworkCoordinate::<2>::new();
+        assert_eq!(
+            a.try_update_batch_with_rng(
+                [(&b, Duration::from_millis(50)), (&b, Duration::ZERO)],
+                &config,
+                &mut thread_rng()
+            ),
+            Err(UpdateError::RttTooSmall)
+        );
+        assert_eq!(
+            serde_json::to_string(&a).expect("serialization failed during test"),
+            before
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_update_closer() {
+        let s = "{\"position\":[100.0,0.0],\"height\":10.0,\"error\":1.0}";
+        let mut a: NetworkCoordinate<2> =
+            serde_json::from_str(s).expect("deserialization failed during test");
+        let s = "{\"position\":[-100.0,0.0],\"height\":10.0,\"error\":1.0}";
+        let b: NetworkCoordinate<2> =
+            serde_json::from_str(s).expect("deserialization failed during test");
+
+        // estimate is 220ms, but the nodes are actually much closer, so `a` should move toward `b`
+        // along the x axis and shrink its height
+        let before = a.estimated_rtt(&b);
+        a.update(&b, Duration::from_millis(50));
+        assert!(a.estimated_rtt(&b) < before);
+        assert!(a.point.is_valid());
+        let json = serde_json::to_value(&a).expect("serialization failed during test");
+        assert!(
+            json["position"][0]
+                .as_f64()
+                .expect("bad position during test")
+                < 100.0
+        );
+        assert_approx_eq!(
+            json["position"][1]
+                .as_f64()
+                .expect("bad position during test"),
+            0.0
+        );
+        assert!(json["height"].as_f64().expect("bad height during test") < 10.0);
+
+        // a big move toward `b` clamps the height to the configured minimum
+        let config = VivaldiConfig::builder()
+            .c_delta(1.0)
+            .min_height(2.0)
+            .build()
+            .expect("valid config rejected during test");
+        a.update_with_config(&b, Duration::from_millis(1), &config);
+        let json = serde_json::to_value(&a).expect("serialization failed during test");
+        assert_approx_eq!(
+            json["height"].as_f64().expect("bad height during test"),
+            2.0
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_try_update_invalid() {
+        let s = "{\"position\":[1.5,0.5,2.0],\"height\":25.0,\"error\":1.0}";
+        let a: NetworkCoordinate<3> =
+            serde_json::from_str(s).expect("deserialization failed during test");
+        let rtt = Duration::from_millis(100);
+
+        // zero RTT
+        let mut b = a.clone();
+        assert_eq!(
+            b.try_update(&a, Duration::ZERO),
+            Err(UpdateError::RttTooSmall)
+        );
+
+        // bad remote error estimates
+        let mut remote = a.clone();
+        remote.error = f64::NAN;
+        assert_eq!(
+            b.try_update(&remote, rtt),
+            Err(UpdateError::InvalidRemoteError)
+        );
+        remote.error = 0.0;
+        assert_eq!(
+            b.try_update(&remote, rtt),
+            Err(UpdateError::InvalidRemoteError)
+        );
+        remote.error = -1.0;
+        assert_eq!(
+            b.try_update(&remote, rtt),
+            Err(UpdateError::InvalidRemoteError)
+        );
+
+        // bad remote point, scaling by a negative factor gives a negative height
+        let mut remote = a.clone();
+        remote.point = remote.point * -1.0;
+        assert_eq!(
+            b.try_update(&remote, rtt),
+            Err(UpdateError::InvalidRemoteCoordinate)
+        );
+
+        // numeric blow-up from a corrupted local error estimate
+        let mut local = a.clone();
+        local.error = f64::INFINITY;
+        assert_eq!(local.try_update(&a, rtt), Err(UpdateError::NumericOverflow));
+
+        // none of those touched the local coordinate
+        assert_eq!(serde_json::to_string(&b).ok(), Some(s.to_string()));
+
+        // `update()` ignores bad samples too
+        b.update(&a, Duration::ZERO);
+        assert_eq!(serde_json::to_string(&b).ok(), Some(s.to_string()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_adjustment() {
+        let config = VivaldiConfig::builder()
+            .adjustment_window(4)
+            .build()
+            .expect("valid config rejected during test");
+        let s = "{\"position\":[0.0,0.0],\"height\":10.0,\"error\":1.0}";
+        let mut a: NetworkCoordinate<2> =
+            serde_json::from_str(s).expect("deserialization failed during test");
+        let b = a.clone();
+        assert_eq!(a.adjustment(), None);
+
+        // without adjustments enabled, nothing is tracked or serialized
+        a.update(&b, Duration::from_millis(50));
+        assert_eq!(a.adjustment(), None);
+        assert!(!serde_json::to_string(&a)
+            .expect("serialization failed during test")
+            .contains("adjustment"));
+
+        // residual is the measured RTT minus the estimate after the update
+        a.update_with_config(&b, Duration::from_millis(50), &config);
+        let residual = 50.0 - (a.point - b.point).len();
+        assert_approx_eq!(a.adjustment().expect("missing adjustment"), residual / 8.0);
+
+        // the adjustment is added to the estimate
+        let raw = (a.point - b.point).len();
+        let estimate = a.estimated_rtt(&b).as_secs_f64() * 1000.0;
+        assert_approx_eq!(estimate, raw + residual / 8.0, 0.01);
+
+        // and serialized
+        let json = serde_json::to_value(&a).expect("serialization failed during test");
+        assert!(json["adjustment"].is_number());
+        let c: NetworkCoordinate<2> =
+            serde_json::from_value(json).expect("deserialization failed during test");
+        assert_eq!(c.adjustment(), a.adjustment());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gravity() {
+        let config = VivaldiConfig::builder()
+            .gravity_rho(Some(100.0))
+            .build()
+            .expect("valid config rejected during test");
+        let s = "{\"position\":[1000.0,0.0],\"height\":10.0,\"error\":1.0}";
+        let a: NetworkCoordinate<2> =
+            serde_json::from_str(s).expect("deserialization failed during test");
+        let s = "{\"position\":[1100.0,0.0],\"height\":10.0,\"error\":1.0}";
+        let b: NetworkCoordinate<2> =
+            serde_json::from_str(s).expect("deserialization failed during test");
+
+        // the same sample moves `a` closer to the origin with gravity than without
+        let rtt = Duration::from_millis(120);
+        let mut without = a.clone();
+        without.update(&b, rtt);
+        let mut with = a;
+        with.update_with_config(&b, rtt, &config);
+        let x = |nc: &NetworkCoordinate<2>| {
+            serde_json::to_value(nc).expect("serialization failed during test")["position"][0]
+                .as_f64()
+                .expect("bad position during test")
+        };
+        assert!(x(&with) < x(&without));
+        assert!(with.point.is_valid());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_update_rules() {
+        let s = "{\"position\":[100.0,0.0],\"height\":10.0,\"error\":3.0}";
+        let b: NetworkCoordinate<2> =
+            serde_json::from_str(s).expect("deserialization failed during test");
+
+        // estimate is 120ms, measured is 220ms, so the local coordinate moves δ × 100ms away
+        // from `b`
+        let moved = |rule, error| {
+            let config = VivaldiConfig::builder()
+                .update_rule(rule)
+                .build()
+                .expect("valid config rejected during test");
+            let a = NetworkCoordinate::<2>::from_parts([0.0, 0.0], 10.0, error)
+                .expect("valid parts rejected during test");
+            let mut local = a.clone();
+            local.update_with_config(&b, Duration::from_millis(220), &config);
+            local.point.len() - a.point.len()
+        };
+        // δ = cc = 0.25
+        assert_approx_eq!(moved(UpdateRule::Constant, 0.4), 25.0, 0.001);
+        // δ = cc × ei / (ei + ej) = 0.25 × 0.4 / 3.4
+        assert_approx_eq!(moved(UpdateRule::Adaptive, 0.4), 2.941, 0.001);
+        // δ = cc × min(ei, 1) = 0.25 × 0.4
+        assert_approx_eq!(moved(UpdateRule::LocalError, 0.4), 10.0, 0.001);
+        // the local error is clamped to 1, so δ = cc
+        assert_approx_eq!(moved(UpdateRule::LocalError, 2.0), 25.0, 0.001);
+    }
+
     #[test]
     fn test_error_getter() {
         let s = "{\"position\":[1.5,0.5,2.0],\"height\":25.0,\"error\":1.0}";
@@ -397,4 +1673,53 @@ mod tests {
             serde_json::from_str(s).expect("deserialization failed during test");
         assert_approx_eq!(a.error(), 1.0);
     }
+
+    #[test]
+    fn test_parts() {
+        let s = "{\"position\":[1.5,0.5,2.0],\"height\":25.0,\"error\":1.0}";
+        let a: NetworkCoordinate<3> =
+            serde_json::from_str(s).expect("deserialization failed during test");
+        assert_approx_eq!(a.position()[0], 1.5);
+        assert_approx_eq!(a.position()[2], 2.0);
+        assert_approx_eq!(a.height(), 25.0);
+
+        // round trip through the parts
+        let b = NetworkCoordinate::<3>::from_parts(a.position(), a.height(), a.error())
+            .expect("valid parts rejected during test");
+        assert_eq!(
+            serde_json::to_string(&b).expect("serialization failed during test"),
+            s
+        );
+
+        // same validation as deserialization
+        assert_eq!(
+            NetworkCoordinate::<2>::from_parts([f64::NAN, 0.0], 1.0, 1.0).err(),
+            Some(CoordinateError::NonFinitePosition)
+        );
+        assert_eq!(
+            NetworkCoordinate::<2>::from_parts([0.0, 0.0], -1.0, 1.0).err(),
+            Some(CoordinateError::InvalidHeight)
+        );
+        assert_eq!(
+            NetworkCoordinate::<2>::from_parts([0.0, 0.0], 1.0, 0.0).err(),
+            Some(CoordinateError::InvalidError)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_from_point() {
+        let point = SphericalVector::<3>::random_with_radius(80.0).expect("valid radius rejected");
+        let a: NetworkCoordinate<3, f64, SphericalVector<3>> =
+            NetworkCoordinate::from_point(point).expect("valid point rejected during test");
+        assert_eq!(a.point, point);
+        assert_approx_eq!(a.error(), VivaldiConfig::<f64>::default().default_error());
+
+        // scaling by a negative factor gives a negative height
+        let point = HeightVector::<2>::new([1.0, 2.0], 3.0).expect("valid point rejected") * -1.0;
+        assert_eq!(
+            NetworkCoordinate::<2>::from_point(point).err(),
+            Some(CoordinateError::InvalidPoint)
+        );
+    }
 }
@@ -0,0 +1,88 @@
+//! The floating point types coordinates can be computed with.
+//!
+//! Every type in this crate which does math is generic over a [`Real`], defaulting to `f64`. The
+//! precision is part of the type, so `f32` and `f64` coordinates can be used side by side in the
+//! same program.
+
+use core::fmt::Debug;
+
+use num_traits::Float;
+use rand::Rng;
+use serde::{de::DeserializeOwned, Serialize};
+
+//
+// **** Traits ****
+//
+
+/// A floating point type which coordinates can be computed with, i.e. `f32` or `f64`.
+///
+/// This trait is sealed, it can't be implemented outside of this crate.
+pub trait Real:
+    Float + Default + Debug + Send + Sync + Serialize + DeserializeOwned + 'static + private::Sealed
+{
+    /// Converts an `f64` to this type, rounding to the nearest value if needed.
+    fn from_f64(value: f64) -> Self;
+
+    /// Converts this value to an `f64`.
+    fn as_f64(self) -> f64;
+
+    /// A uniformly distributed random value in `[0, 1)`.
+    fn sample<R: Rng + ?Sized>(rng: &mut R) -> Self;
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
+}
+
+//
+// **** Trait Implementations ****
+//
+
+impl Real for f32 {
+    #[allow(clippy::cast_possible_truncation)]
+    fn from_f64(value: f64) -> Self {
+        value as Self
+    }
+
+    fn as_f64(self) -> f64 {
+        f64::from(self)
+    }
+
+    fn sample<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        rng.gen()
+    }
+}
+
+impl Real for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn as_f64(self) -> f64 {
+        self
+    }
+
+    fn sample<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        rng.gen()
+    }
+}
+
+//
+// **** Tests ****
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert!((f32::from_f64(0.1).as_f64() - 0.1).abs() < 1e-6);
+        assert!((f64::from_f64(0.1).as_f64() - 0.1).abs() < f64::EPSILON);
+
+        let mut rng = rand::thread_rng();
+        let x = f32::sample(&mut rng);
+        assert!((0.0..1.0).contains(&x));
+    }
+}
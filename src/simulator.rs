@@ -0,0 +1,546 @@
+//! Simulation of a network of nodes running Vivaldi against known RTTs, for evaluating tuning
+//! parameters and latency spaces offline.
+//!
+//! A [`Simulator`] owns one [`NetworkCoordinate`] per node and a [`LatencySource`] which provides
+//! the ground truth RTTs between them. It advances in rounds: in each round, every node picks a
+//! peer with a [`PeerSelector`], "measures" the RTT to it by looking it up in the source, and
+//! updates its coordinate with it, just like it would in a live network. All randomness comes from
+//! a seeded RNG, so a simulation can be repeated exactly.
+//!
+//! # Example
+//!
+//! ```
+//! use core::time::Duration;
+//! use vivaldi_nc::simulator::{RandomPeer, Simulator};
+//!
+//! // four nodes on a line, 20ms apart
+//! let rtts: Vec<Vec<Option<Duration>>> = (0..4u64)
+//!     .map(|i| (0..4u64).map(|j| Some(Duration::from_millis(20 * i.abs_diff(j)))).collect())
+//!     .collect();
+//!
+//! let mut simulator = Simulator::<2, _>::new(rtts, RandomPeer, 42);
+//!
+//! // run until the nodes are confident in their coordinates, or give up after 500 rounds
+//! let stats = simulator.rounds().take(500).find(|stats| stats.mean_error < 0.05);
+//! assert!(stats.is_some());
+//! ```
+
+use core::time::Duration;
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::config::VivaldiConfig;
+use crate::height_vector::HeightVector;
+use crate::latency_space::LatencySpace;
+use crate::metrics::{AccuracyReport, Snapshot};
+use crate::network_coordinate::NetworkCoordinate;
+use crate::real::Real;
+
+//
+// **** Traits ****
+//
+
+/// The ground truth RTTs between the nodes of a simulated network.
+pub trait LatencySource {
+    /// The number of nodes in the network.
+    fn size(&self) -> usize;
+
+    /// The RTT from node `from` to node `to` in the given (zero based) round, or `None` if it
+    /// can't be measured. The round allows RTTs to change over time, e.g. by cycling through the
+    /// time slices of a dataset.
+    fn rtt(&self, from: usize, to: usize, round: usize) -> Option<Duration>;
+}
+
+/// A policy for picking the peer each node measures in a round.
+///
+/// Any `FnMut(local, size, rng) -> Option<usize>` closure is a `PeerSelector` too.
+pub trait PeerSelector {
+    /// Picks the peer node `local` measures next, out of `size` nodes, or `None` to skip `local`
+    /// this round.
+    fn select(&mut self, local: usize, size: usize, rng: &mut StdRng) -> Option<usize>;
+}
+
+//
+// **** Structs ****
+//
+
+/// Picks a uniformly random peer out of all other nodes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RandomPeer;
+
+/// Picks a uniformly random peer out of a fixed set of neighbors per node, like the
+/// neighbor sets in the Vivaldi paper.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NeighborSet {
+    neighbors: Vec<Vec<usize>>,
+}
+
+/// Statistics about a single round of a [`Simulator`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[allow(clippy::derive_partial_eq_without_eq)] // NOTE: only `PartialEq` for float `T`
+pub struct RoundStats<T = f64> {
+    /// The number of rounds completed, including this one.
+    pub round: usize,
+    /// The number of updates applied in this round.
+    pub samples: usize,
+    /// The number of nodes which didn't update in this round, because no peer was selected, the
+    /// RTT was missing, or the sample was rejected.
+    pub skipped: usize,
+    /// The mean error estimate ([`NetworkCoordinate::error`]) of all nodes after this round.
+    pub mean_error: T,
+    /// The mean relative error of the RTT estimates for this round's samples, computed before each
+    /// update, or zero if there were no samples.
+    pub mean_sample_error: T,
+}
+
+/// A simulated network of nodes running Vivaldi.
+///
+/// ## Generic Parameters
+///
+/// - `N`: the dimensionality of the coordinates
+/// - `L`: the [`LatencySource`] with the ground truth RTTs
+/// - `P`: the [`PeerSelector`], [`RandomPeer`] by default
+/// - `T`: the floating point type of the coordinates, `f64` by default
+/// - `S`: the [`LatencySpace`] of the coordinates, [`HeightVector`] by default
+#[derive(Clone, Debug)]
+pub struct Simulator<const N: usize, L, P = RandomPeer, T = f64, S = HeightVector<N, T>>
+where
+    T: Real,
+    S: LatencySpace<T>,
+{
+    nodes: Vec<NetworkCoordinate<N, T, S>>,
+    source: L,
+    selector: P,
+    config: VivaldiConfig<T>,
+    rng: StdRng,
+    round: usize,
+}
+
+/// Iterator returned by [`Simulator::rounds`], which runs one round per item, forever.
+#[derive(Debug)]
+pub struct Rounds<'a, const N: usize, L, P, T, S>
+where
+    T: Real,
+    S: LatencySpace<T>,
+{
+    simulator: &'a mut Simulator<N, L, P, T, S>,
+}
+
+//
+// **** Implementations ****
+//
+
+impl NeighborSet {
+    /// Creates a selector from explicit neighbor lists, where `neighbors[i]` are the nodes node
+    /// `i` may pick. Nodes with an empty list are skipped.
+    #[must_use]
+    pub const fn new(neighbors: Vec<Vec<usize>>) -> Self {
+        Self { neighbors }
+    }
+
+    /// Gives each of `size` nodes `count` distinct random neighbors (or all other nodes, if there
+    /// are fewer).
+    pub fn random<R: Rng + ?Sized>(size: usize, count: usize, rng: &mut R) -> Self {
+        let others = size.saturating_sub(1);
+        let neighbors = (0..size)
+            .map(|local| {
+                rand::seq::index::sample(rng, others, count.min(others))
+                    .into_iter()
+                    .map(|j| if j < local { j } else { j + 1 })
+                    .collect()
+            })
+            .collect();
+        Self { neighbors }
+    }
+
+    /// The neighbors of node `local`.
+    #[must_use]
+    pub fn neighbors(&self, local: usize) -> &[usize] {
+        self.neighbors.get(local).map_or(&[], Vec::as_slice)
+    }
+}
+
+impl<const N: usize, L, P, T, S> Simulator<N, L, P, T, S>
+where
+    L: LatencySource,
+    P: PeerSelector,
+    T: Real,
+    S: LatencySpace<T>,
+{
+    /// Creates a simulator with the default [`VivaldiConfig`], where every node starts at a
+    /// random position.
+    ///
+    /// # Parameters
+    ///
+    /// - `source`: the ground truth RTTs, which also determines the number of nodes
+    /// - `selector`: the policy for picking peers
+    /// - `seed`: the seed for all randomness, the same seed always gives the same simulation
+    #[must_use]
+    pub fn new(source: L, selector: P, seed: u64) -> Self {
+        Self::new_with_config(source, selector, seed, VivaldiConfig::default())
+    }
+
+    /// Same as [`Simulator::new`], with custom tuning parameters.
+    #[must_use]
+    pub fn new_with_config(source: L, selector: P, seed: u64, config: VivaldiConfig<T>) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let nodes = (0..source.size())
+            .map(|_| {
+                let mut nc = NetworkCoordinate::new_with_rng(&mut rng);
+                nc.set_error(config.default_error());
+                nc
+            })
+            .collect();
+        Self {
+            nodes,
+            source,
+            selector,
+            config,
+            rng,
+            round: 0,
+        }
+    }
+
+    /// Runs a single round, in which every node (in order) measures one peer and updates its
+    /// coordinate.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn step(&mut self) -> RoundStats<T> {
+        let size = self.nodes.len();
+        let mut samples = 0;
+        let mut sample_error = T::zero();
+
+        for local in 0..size {
+            let Some(remote) = self
+                .selector
+                .select(local, size, &mut self.rng)
+                .filter(|&remote| remote != local && remote < size)
+            else {
+                continue;
+            };
+            let Some(rtt) = self.source.rtt(local, remote, self.round) else {
+                continue;
+            };
+
+            let remote = self.nodes[remote].clone();
+            let estimate = self.nodes[local].estimated_rtt(&remote).as_secs_f64();
+            if self.nodes[local]
+                .try_update_with_rng(&remote, rtt, &self.config, &mut self.rng)
+                .is_ok()
+            {
+                let measured = rtt.as_secs_f64();
+                sample_error = sample_error + T::from_f64((estimate - measured).abs() / measured);
+                samples += 1;
+            }
+        }
+
+        self.round += 1;
+        RoundStats {
+            round: self.round,
+            samples,
+            skipped: size - samples,
+            mean_error: self.mean_error(),
+            mean_sample_error: if samples == 0 {
+                T::zero()
+            } else {
+                sample_error / T::from_f64(samples as f64)
+            },
+        }
+    }
+
+    /// Runs `rounds` rounds and returns the statistics of the last one, or `None` if `rounds` is
+    /// zero.
+    pub fn run(&mut self, rounds: usize) -> Option<RoundStats<T>> {
+        self.rounds().take(rounds).last()
+    }
+
+    /// Returns an iterator which runs a round per item and yields its statistics. It never ends
+    /// by itself, so use adapters like `take()`, `find()`, or `take_while()` to stop it, or
+    /// `inspect()` to report progress.
+    pub fn rounds(&mut self) -> Rounds<'_, N, L, P, T, S> {
+        Rounds { simulator: self }
+    }
+
+    /// Compares the current coordinates to the ground truth, see [`AccuracyReport::new`].
+    #[must_use]
+    pub fn accuracy(&self) -> Option<AccuracyReport> {
+        AccuracyReport::new(&self.nodes, &self.source)
+    }
+
+    /// The mean error estimate of all nodes, or zero if there are none.
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn mean_error(&self) -> T {
+        if self.nodes.is_empty() {
+            return T::zero();
+        }
+        self.nodes
+            .iter()
+            .fold(T::zero(), |sum, nc| sum + nc.error())
+            / T::from_f64(self.nodes.len() as f64)
+    }
+
+    /// The coordinates of all nodes, in the order of the source.
+    #[must_use]
+    pub fn coordinates(&self) -> &[NetworkCoordinate<N, T, S>] {
+        &self.nodes
+    }
+
+    /// The ground truth RTTs.
+    #[must_use]
+    pub const fn source(&self) -> &L {
+        &self.source
+    }
+
+    /// The tuning parameters used for the updates.
+    #[must_use]
+    pub const fn config(&self) -> &VivaldiConfig<T> {
+        &self.config
+    }
+
+    /// The number of rounds completed so far.
+    #[must_use]
+    pub const fn round(&self) -> usize {
+        self.round
+    }
+
+    /// Takes a [`Snapshot`] of the current coordinates, for measuring their stability. Each round
+    /// counts as one second, so rates are per round.
+    #[must_use]
+    pub fn snapshot(&self) -> Snapshot<N, T, S> {
+        let time = Duration::from_secs(u64::try_from(self.round).unwrap_or(u64::MAX));
+        Snapshot::new(time, self.nodes.clone())
+    }
+}
+
+//
+// **** Trait Implementations ****
+//
+
+impl<M> LatencySource for [M]
+where
+    M: AsRef<[Option<Duration>]>,
+{
+    fn size(&self) -> usize {
+        self.len()
+    }
+
+    fn rtt(&self, from: usize, to: usize, _round: usize) -> Option<Duration> {
+        self.get(from)?.as_ref().get(to).copied().flatten()
+    }
+}
+
+impl<M> LatencySource for Vec<M>
+where
+    M: AsRef<[Option<Duration>]>,
+{
+    fn size(&self) -> usize {
+        self.as_slice().size()
+    }
+
+    fn rtt(&self, from: usize, to: usize, round: usize) -> Option<Duration> {
+        self.as_slice().rtt(from, to, round)
+    }
+}
+
+impl<L> LatencySource for &L
+where
+    L: LatencySource + ?Sized,
+{
+    fn size(&self) -> usize {
+        (**self).size()
+    }
+
+    fn rtt(&self, from: usize, to: usize, round: usize) -> Option<Duration> {
+        (**self).rtt(from, to, round)
+    }
+}
+
+#[cfg(feature = "datasets")]
+impl LatencySource for crate::datasets::LatencyMatrix {
+    fn size(&self) -> usize {
+        self.size()
+    }
+
+    fn rtt(&self, from: usize, to: usize, _round: usize) -> Option<Duration> {
+        self.get(from, to)
+    }
+}
+
+/// Time slices of the same network, like the numbered matrices of a dataset, cycled through one
+/// per round. All matrices should have the same size, the size of the first one is used.
+#[cfg(feature = "datasets")]
+impl LatencySource for [crate::datasets::LatencyMatrix] {
+    fn size(&self) -> usize {
+        self.first().map_or(0, crate::datasets::LatencyMatrix::size)
+    }
+
+    fn rtt(&self, from: usize, to: usize, round: usize) -> Option<Duration> {
+        self.get(round.checked_rem(self.len())?)?.get(from, to)
+    }
+}
+
+#[cfg(feature = "datasets")]
+impl LatencySource for Vec<crate::datasets::LatencyMatrix> {
+    fn size(&self) -> usize {
+        self.as_slice().size()
+    }
+
+    fn rtt(&self, from: usize, to: usize, round: usize) -> Option<Duration> {
+        self.as_slice().rtt(from, to, round)
+    }
+}
+
+impl PeerSelector for RandomPeer {
+    fn select(&mut self, local: usize, size: usize, rng: &mut StdRng) -> Option<usize> {
+        if size < 2 {
+            return None;
+        }
+        // pick out of the other size - 1 nodes, skipping over `local`
+        let remote = rng.gen_range(0..size - 1);
+        Some(if remote < local { remote } else { remote + 1 })
+    }
+}
+
+impl PeerSelector for NeighborSet {
+    fn select(&mut self, local: usize, _size: usize, rng: &mut StdRng) -> Option<usize> {
+        self.neighbors(local).choose(rng).copied()
+    }
+}
+
+impl<F> PeerSelector for F
+where
+    F: FnMut(usize, usize, &mut StdRng) -> Option<usize>,
+{
+    fn select(&mut self, local: usize, size: usize, rng: &mut StdRng) -> Option<usize> {
+        self(local, size, rng)
+    }
+}
+
+impl<const N: usize, L, P, T, S> Iterator for Rounds<'_, N, L, P, T, S>
+where
+    L: LatencySource,
+    P: PeerSelector,
+    T: Real,
+    S: LatencySpace<T>,
+{
+    type Item = RoundStats<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.simulator.step())
+    }
+}
+
+//
+// **** Tests ****
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::StabilityReport;
+
+    /// RTTs between nodes on a line, 10ms apart.
+    fn line(size: u64) -> Vec<Vec<Option<Duration>>> {
+        (0..size)
+            .map(|i| {
+                (0..size)
+                    .map(|j| Some(Duration::from_millis(10 * i.abs_diff(j))))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_simulate() {
+        let mut simulator = Simulator::<2, _>::new(line(8), RandomPeer, 1);
+        let first = simulator.step();
+        assert_eq!(first.round, 1);
+        assert_eq!(first.samples + first.skipped, 8);
+
+        let last = simulator.run(999).expect("no rounds run during test");
+        assert_eq!(last.round, 1000);
+        assert_eq!(simulator.round(), 1000);
+        assert!(last.mean_error < first.mean_error);
+        assert!(last.mean_sample_error < 0.05);
+
+        let nc = simulator.coordinates();
+        let estimate = nc[0].estimated_rtt(&nc[7]).as_secs_f64();
+        assert!((estimate - 0.070).abs() < 0.005);
+
+        let report = simulator.accuracy().expect("no pairs during test");
+        assert_eq!(report.pairs(), 56);
+        assert!(report.median_relative_error() < 0.05);
+
+        // once settled, coordinates hardly move any more
+        let previous = simulator.snapshot();
+        simulator.run(10);
+        let stability = StabilityReport::new(&previous, &simulator.snapshot())
+            .expect("valid snapshots rejected");
+        assert!(stability.mean_displacement_rate() < 0.1);
+    }
+
+    #[test]
+    fn test_seeded() {
+        let stats = |seed| {
+            let mut simulator = Simulator::<2, _>::new(line(5), RandomPeer, seed);
+            simulator.rounds().take(20).collect::<Vec<_>>()
+        };
+        assert_eq!(stats(7), stats(7));
+        assert_ne!(stats(7), stats(8));
+    }
+
+    #[test]
+    fn test_missing() {
+        // node 2 hasn't measured anyone, and nobody has measured node 2
+        let mut rtts = line(3);
+        rtts[2] = vec![None; 3];
+        // node 0 measures node 1, everybody else measures node 0
+        let selector = |local, _, _: &mut StdRng| Some(usize::from(local == 0));
+        let mut simulator = Simulator::<2, _, _>::new(&rtts, selector, 3);
+        let stats = simulator.step();
+        assert_eq!(stats.samples, 2);
+        assert_eq!(stats.skipped, 1);
+    }
+
+    #[cfg(feature = "datasets")]
+    #[test]
+    fn test_time_slices() {
+        use crate::datasets::{DatasetFormat, LatencyMatrix};
+
+        let slices = [
+            LatencyMatrix::parse("0 10\n10 0", DatasetFormat::PlanetLab),
+            LatencyMatrix::parse("0 20\n20 0", DatasetFormat::PlanetLab),
+        ]
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .expect("valid matrix rejected during test");
+        assert_eq!(slices.size(), 2);
+        assert_eq!(slices.rtt(0, 1, 0), Some(Duration::from_millis(10)));
+        assert_eq!(slices.rtt(0, 1, 1), Some(Duration::from_millis(20)));
+        assert_eq!(slices.rtt(0, 1, 2), Some(Duration::from_millis(10)));
+        assert_eq!(slices.rtt(0, 0, 1), None);
+
+        let empty: &[LatencyMatrix] = &[];
+        assert_eq!(empty.size(), 0);
+        assert_eq!(empty.rtt(0, 1, 3), None);
+    }
+
+    #[test]
+    fn test_neighbor_set() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut selector = NeighborSet::random(10, 3, &mut rng);
+        for local in 0..10 {
+            let neighbors = selector.neighbors(local).to_vec();
+            assert_eq!(neighbors.len(), 3);
+            assert!(neighbors.iter().all(|&j| j != local && j < 10));
+            for _ in 0..10 {
+                let remote = selector.select(local, 10, &mut rng);
+                assert!(remote.map_or(false, |remote| neighbors.contains(&remote)));
+            }
+        }
+
+        // not enough nodes for that many neighbors
+        assert_eq!(NeighborSet::random(2, 3, &mut rng).neighbors(0), &[1]);
+        assert!(NeighborSet::new(vec![]).neighbors(0).is_empty());
+        assert_eq!(RandomPeer.select(0, 1, &mut rng), None);
+    }
+}
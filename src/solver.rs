@@ -0,0 +1,306 @@
+//! Centralized solver which computes coordinates for every node of a known RTT matrix.
+//!
+//! Vivaldi is meant to run distributed, with every node updating its own coordinate as it measures
+//! RTTs to its peers. When a complete (or mostly complete) RTT matrix is already available in one
+//! place, e.g. in a monitoring backend, there's no need to simulate that gossip loop. This module
+//! runs the centralized spring relaxation from the paper instead: every round, every node is moved
+//! by the summed forces of all of its measured RTTs at once (see
+//! [`NetworkCoordinate::update_batch`]).
+//!
+//! # Example
+//!
+//! ```
+//! use core::time::Duration;
+//! use vivaldi_nc::solver::{solve_from_matrix, SolverOptions};
+//! use vivaldi_nc::NetworkCoordinate;
+//!
+//! let ms = |ms| Some(Duration::from_millis(ms));
+//! // the RTT between the second and third node wasn't measured
+//! let rtts = [
+//!     [None, ms(40), ms(60)],
+//!     [ms(40), None, None],
+//!     [ms(60), None, None],
+//! ];
+//!
+//! let nc: Vec<NetworkCoordinate<2>> = solve_from_matrix(&rtts, &SolverOptions::default()).unwrap();
+//! let estimate = nc[0].estimated_rtt(&nc[1]).as_secs_f64();
+//! assert!((estimate - 0.040).abs() < 0.002);
+//! ```
+
+use core::time::Duration;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::config::VivaldiConfig;
+use crate::error::SolverError;
+use crate::network_coordinate::NetworkCoordinate;
+use crate::real::Real;
+
+//
+// **** Constants ****
+//
+
+// enough rounds for a few hundred nodes to settle
+const DEFAULT_ROUNDS: usize = 1000;
+
+//
+// **** Structs ****
+//
+
+/// Options for [`solve_from_matrix`].
+///
+/// ## Generic Parameters
+///
+/// - `T`: the floating point type of the coordinates to solve for, `f64` by default
+#[derive(Copy, Clone, Debug)]
+pub struct SolverOptions<T = f64>
+where
+    T: Real,
+{
+    rounds: usize,
+    seed: u64,
+    config: VivaldiConfig<T>,
+}
+
+//
+// **** Implementations ****
+//
+
+impl<T> SolverOptions<T>
+where
+    T: Real,
+{
+    /// Sets the number of relaxation rounds. Every node is updated once per round.
+    #[must_use]
+    pub const fn rounds(mut self, rounds: usize) -> Self {
+        self.rounds = rounds;
+        self
+    }
+
+    /// Sets the seed for the initial random positions, the same seed always gives the same
+    /// coordinates.
+    #[must_use]
+    pub const fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the Vivaldi tuning parameters used for the updates.
+    #[must_use]
+    pub const fn config(mut self, config: VivaldiConfig<T>) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+//
+// **** Trait Implementations ****
+//
+
+impl<T> Default for SolverOptions<T>
+where
+    T: Real,
+{
+    /// 1000 rounds with a fixed seed and the default [`VivaldiConfig`].
+    fn default() -> Self {
+        Self {
+            rounds: DEFAULT_ROUNDS,
+            seed: 0,
+            config: VivaldiConfig::default(),
+        }
+    }
+}
+
+//
+// **** Functions ****
+//
+
+/// Computes a coordinate for every node of an RTT matrix.
+///
+/// Every node starts at a random position. In each round, all nodes are moved at once by the
+/// summed forces of their measured RTTs, using the positions from the end of the previous round,
+/// so the result doesn't depend on the order of the nodes. Once the rounds are done, each node's
+/// error is set to the mean relative error of its estimates against its measured RTTs.
+///
+/// # Parameters
+///
+/// - `rtts`: a square matrix where `rtts[i][j]` is the RTT measured from node `i` to node `j`, or
+///   `None` if it's missing. Zero RTTs (like the diagonal) are treated as missing too.
+/// - `options`: the number of rounds, seed, and Vivaldi tuning parameters
+///
+/// # Returns
+///
+/// - one coordinate per row of `rtts`. Nodes without any measured RTTs keep their random position
+///   and the default error.
+///
+/// # Errors
+///
+/// - [`SolverError::NotSquare`] if a row of `rtts` doesn't have one entry per row
+pub fn solve_from_matrix<const N: usize, T, M>(
+    rtts: &[M],
+    options: &SolverOptions<T>,
+) -> Result<Vec<NetworkCoordinate<N, T>>, SolverError>
+where
+    T: Real,
+    M: AsRef<[Option<Duration>]>,
+{
+    let size = rtts.len();
+    if rtts.iter().any(|row| row.as_ref().len() != size) {
+        return Err(SolverError::NotSquare);
+    }
+
+    // the measured (non-zero) RTTs of each node
+    let samples: Vec<Vec<(usize, Duration)>> = rtts
+        .iter()
+        .map(|row| {
+            row.as_ref()
+                .iter()
+                .enumerate()
+                .filter_map(|(j, rtt)| rtt.filter(|rtt| !rtt.is_zero()).map(|rtt| (j, rtt)))
+                .collect()
+        })
+        .collect();
+
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let mut nc: Vec<NetworkCoordinate<N, T>> = (0..size)
+        .map(|_| {
+            let mut nc = NetworkCoordinate::new_with_rng(&mut rng);
+            nc.set_error(options.config.default_error());
+            nc
+        })
+        .collect();
+
+    for _ in 0..options.rounds {
+        let previous = nc.clone();
+        for (nc, samples) in nc.iter_mut().zip(&samples) {
+            if samples.is_empty() {
+                continue;
+            }
+            // a rejected batch leaves the node where it was, which is all we can do with it
+            let _ = nc.try_update_batch_with_rng(
+                samples.iter().map(|&(j, rtt)| (&previous[j], rtt)),
+                &options.config,
+                &mut rng,
+            );
+        }
+    }
+
+    // replace the moving average error with the actual error of the final positions
+    let errors: Vec<Option<T>> = nc
+        .iter()
+        .zip(&samples)
+        .map(|(local, samples)| mean_relative_error(local, samples, &nc))
+        .collect();
+    for (nc, error) in nc.iter_mut().zip(errors) {
+        if let Some(error) = error.filter(|error| error.is_finite()) {
+            nc.set_error(error.max(options.config.min_error()));
+        }
+    }
+
+    Ok(nc)
+}
+
+/// The mean relative error of `local`'s estimates against its measured RTTs, or `None` if it
+/// doesn't have any.
+#[allow(clippy::cast_precision_loss)]
+fn mean_relative_error<const N: usize, T>(
+    local: &NetworkCoordinate<N, T>,
+    samples: &[(usize, Duration)],
+    nc: &[NetworkCoordinate<N, T>],
+) -> Option<T>
+where
+    T: Real,
+{
+    if samples.is_empty() {
+        return None;
+    }
+    let sum = samples.iter().fold(T::zero(), |sum, &(j, rtt)| {
+        let measured = rtt.as_secs_f64();
+        let estimated = local.estimated_rtt(&nc[j]).as_secs_f64();
+        sum + T::from_f64((estimated - measured).abs() / measured)
+    });
+    Some(sum / T::from_f64(samples.len() as f64))
+}
+
+//
+// **** Tests ****
+//
+#[cfg(test)]
+mod tests {
+    use assert_approx_eq::assert_approx_eq;
+
+    use super::*;
+
+    /// RTTs between nodes on a line, 10ms apart.
+    fn line(size: usize) -> Vec<Vec<Option<Duration>>> {
+        (0..size)
+            .map(|i| {
+                (0..size)
+                    .map(|j| Some(Duration::from_millis(10 * i.abs_diff(j) as u64)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_solve() {
+        let rtts = line(6);
+        let nc: Vec<NetworkCoordinate<2>> =
+            solve_from_matrix(&rtts, &SolverOptions::default()).expect("valid matrix rejected");
+        assert_eq!(nc.len(), 6);
+        for (i, a) in nc.iter().enumerate() {
+            assert!(a.error() < 0.05);
+            for (j, b) in nc.iter().enumerate().filter(|&(j, _)| j != i) {
+                let measured = rtts[i][j].expect("complete matrix").as_secs_f64();
+                assert_approx_eq!(a.estimated_rtt(b).as_secs_f64(), measured, 0.002);
+            }
+        }
+    }
+
+    #[test]
+    fn test_solve_partial() {
+        // drop every third measurement, the rest still pins everything down
+        let mut rtts = line(6);
+        for (i, row) in rtts.iter_mut().enumerate() {
+            for (j, rtt) in row.iter_mut().enumerate() {
+                if (i + j) % 3 == 0 {
+                    *rtt = None;
+                }
+            }
+        }
+        let nc: Vec<NetworkCoordinate<2>> =
+            solve_from_matrix(&rtts, &SolverOptions::default()).expect("valid matrix rejected");
+        assert_approx_eq!(nc[0].estimated_rtt(&nc[5]).as_secs_f64(), 0.050, 0.005);
+    }
+
+    #[test]
+    fn test_solve_seeded() {
+        let rtts = line(4);
+        let options = SolverOptions::default().rounds(50).seed(7);
+        let a: Vec<NetworkCoordinate<2>> =
+            solve_from_matrix(&rtts, &options).expect("valid matrix rejected");
+        let b: Vec<NetworkCoordinate<2>> =
+            solve_from_matrix(&rtts, &options).expect("valid matrix rejected");
+        assert_eq!(
+            serde_json::to_string(&a).expect("serialization failed during test"),
+            serde_json::to_string(&b).expect("serialization failed during test")
+        );
+    }
+
+    #[test]
+    fn test_solve_invalid() {
+        let rtts = vec![vec![None, Some(Duration::from_millis(10))], vec![None]];
+        assert_eq!(
+            solve_from_matrix::<2, f64, _>(&rtts, &SolverOptions::default()).err(),
+            Some(SolverError::NotSquare)
+        );
+
+        // an empty matrix has no nodes
+        let rtts: [[Option<Duration>; 0]; 0] = [];
+        assert!(
+            solve_from_matrix::<2, f64, _>(&rtts, &SolverOptions::default())
+                .expect("empty matrix rejected")
+                .is_empty()
+        );
+    }
+}
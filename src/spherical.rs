@@ -0,0 +1,426 @@
+//! Implementation of a point on the surface of a sphere.
+//!
+//! The Vivaldi paper also evaluates embedding nodes on the surface of a sphere. The
+//! Internet wraps around the globe, so a message from Asia to the US can take the short way around
+//! instead of crossing Europe, which a flat space can't represent. The distance between two points
+//! is the length of the great circle arc between them:
+//!
+//! ```text
+//! d(x, y) = r × ∠(x, y)
+//! ```
+//!
+//! The radius `r` (in milliseconds) is a parameter of the space. The longest possible distance is
+//! half way around the sphere, `π × r`, so the radius should be large enough for the longest RTTs
+//! in the network. Spheres which are too small can't represent the long RTTs, and fit poorly.
+//!
+//! Points are stored as an N-dimensional position on the sphere, so `SphericalVector<3>` is the
+//! familiar 2D surface of a ball. Vivaldi moves a coordinate along the great circle through it and
+//! its peer. The arithmetic operators work on the position and keep the radius of the left hand
+//! side, so a coordinate never changes its radius during an update.
+
+use core::ops::{Add, Mul, Sub};
+
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::CoordinateError;
+use crate::latency_space::CoordinateModel;
+use crate::real::Real;
+use crate::vector::Vector;
+
+//
+// **** Constants ****
+//
+
+// half way around is a little over 600ms, which covers all but the slowest links on PlanetLab
+const DEFAULT_RADIUS: f64 = 200.0;
+
+// how far off the sphere a deserialized point may be, relative to the radius
+const SURFACE_TOLERANCE: f64 = 1.0e-3;
+
+//
+// **** Structs ****
+//
+
+/// A point (or vector) on the surface of an N-dimensional sphere with radius `r`.
+///
+/// Use it as the [`LatencySpace`](crate::LatencySpace) of a
+/// [`NetworkCoordinate`](crate::NetworkCoordinate) to embed the network on a sphere:
+///
+/// ```
+/// # #[cfg(feature = "std")]
+/// # fn main() {
+/// use core::time::Duration;
+/// use vivaldi_nc::{NetworkCoordinate, SphericalVector};
+///
+/// let point = SphericalVector::<3>::random_with_radius(80.0).unwrap();
+/// let mut local: NetworkCoordinate<3, f64, SphericalVector<3>> = NetworkCoordinate::from_point(point).unwrap();
+/// let remote: NetworkCoordinate<3, f64, SphericalVector<3>> = NetworkCoordinate::new();
+///
+/// local.update(&remote, Duration::from_millis(100));
+/// println!("Estimated RTT: {}", local.estimated_rtt(&remote).as_millis());
+/// # }
+/// # #[cfg(not(feature = "std"))]
+/// # fn main() {}
+/// ```
+///
+/// Both sides of an update should use the same radius. If they don't, the radius of the local
+/// coordinate wins.
+///
+/// # Serialization
+///
+/// The position and radius are serialized next to a `model` discriminator:
+///
+/// ```text
+/// {"model":"spherical","position":[0.0,0.0,80.0],"radius":80.0,"error":1.0}
+/// ```
+///
+/// A deserialized point has to lie on its sphere.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "model",
+    rename = "spherical",
+    try_from = "SphericalVectorRepr<N, T>",
+    bound(serialize = "T: Real", deserialize = "T: Real")
+)]
+pub struct SphericalVector<const N: usize, T = f64>
+where
+    T: Real,
+{
+    #[serde(flatten)]
+    position: Vector<T, N>,
+    radius: T,
+}
+
+/// Unvalidated wire representation of a [`SphericalVector`], only used for deserialization.
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "T: Real"))]
+struct SphericalVectorRepr<const N: usize, T>
+where
+    T: Real,
+{
+    model: CoordinateModel,
+    #[serde(flatten)]
+    position: Vector<T, N>,
+    radius: T,
+}
+
+//
+// **** Implementations ****
+//
+
+impl<const N: usize, T> SphericalVector<N, T>
+where
+    T: Real,
+{
+    /// Creates a point on a sphere with the given `radius`, in the direction of `position`. The
+    /// position is projected onto the sphere, so only its direction matters.
+    ///
+    /// # Errors
+    ///
+    /// - [`CoordinateError::NonFinitePosition`] if a component of `position` is NaN or infinite
+    /// - [`CoordinateError::InvalidRadius`] if `radius` isn't finite and greater than zero
+    /// - [`CoordinateError::OffSphere`] if `position` is zero, which has no direction
+    pub fn new(position: [T; N], radius: T) -> Result<Self, CoordinateError> {
+        let position = Vector::from(position);
+        if position.is_invalid() {
+            return Err(CoordinateError::NonFinitePosition);
+        }
+        if !is_valid_radius(radius) {
+            return Err(CoordinateError::InvalidRadius);
+        }
+        let len = position.len();
+        if len > T::zero() {
+            Ok(Self {
+                position: position * (radius / len),
+                radius,
+            })
+        } else {
+            Err(CoordinateError::OffSphere)
+        }
+    }
+
+    /// Creates a random point on a sphere with the given `radius`.
+    ///
+    /// # Errors
+    ///
+    /// - [`CoordinateError::InvalidRadius`] if `radius` isn't finite and greater than zero
+    #[cfg(feature = "std")]
+    pub fn random_with_radius(radius: T) -> Result<Self, CoordinateError> {
+        if is_valid_radius(radius) {
+            Ok(Self::random_on(radius, &mut thread_rng()))
+        } else {
+            Err(CoordinateError::InvalidRadius)
+        }
+    }
+
+    /// The position of the point on the surface of the sphere.
+    #[must_use]
+    pub const fn position(&self) -> [T; N] {
+        self.position.to_array()
+    }
+
+    /// The radius of the sphere this point lives on, in milliseconds.
+    #[must_use]
+    pub const fn radius(&self) -> T {
+        self.radius
+    }
+
+    /// A random point on a sphere with the default radius, drawn from `rng`.
+    pub(crate) fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::random_on(T::from_f64(DEFAULT_RADIUS), rng)
+    }
+
+    /// A random point on a sphere with the given `radius`, drawn from `rng`.
+    pub(crate) fn random_on<R: Rng + ?Sized>(radius: T, rng: &mut R) -> Self {
+        let mut vec = [T::zero(); N];
+        for i in vec.iter_mut().take(N) {
+            *i = T::sample(rng) - T::from_f64(0.5);
+        }
+        let ret = Self {
+            position: Vector::from(vec),
+            radius,
+        };
+        if ret.position.len() > T::zero() {
+            ret.projected()
+        } else {
+            // all components came up as exactly 0.5, try again
+            Self::random_on(radius, rng)
+        }
+    }
+
+    /// The great circle distance between two points, using the radius of `self`.
+    pub(crate) fn distance(&self, rhs: &Self) -> T {
+        let x = self.position / self.position.len();
+        let y = rhs.position / rhs.position.len();
+
+        // the angle between two unit vectors, without the cancellation `acos(x·y)` suffers from
+        // for nearby points
+        T::from_f64(2.0) * (x - y).len().atan2((x + y).len()) * self.radius
+    }
+
+    /// Moves `self` by `distance` along the great circle through `self` and `rhs`, away from `rhs`
+    /// (or toward it if `distance` is negative).
+    pub(crate) fn moved_away<R: Rng + ?Sized>(&self, rhs: &Self, distance: T, rng: &mut R) -> Self {
+        let x = self.position / self.position.len();
+        let y = rhs.position / rhs.position.len();
+
+        // the direction away from `rhs`, tangent to the sphere at `self`
+        let mut tangent = x * x.dot(&y) - y;
+        if tangent.len() <= T::epsilon() {
+            // the points coincide (or are opposite each other), so any direction will do
+            let r = Self::random_on(T::one(), rng).position;
+            tangent = r - x * x.dot(&r);
+        }
+        let len = tangent.len();
+        if len <= T::zero() {
+            return *self;
+        }
+
+        let angle = distance / self.radius;
+        Self {
+            position: (x * angle.cos() + tangent * (angle.sin() / len)) * self.radius,
+            radius: self.radius,
+        }
+    }
+
+    /// This vector scaled to unit magnitude, or a random unit vector if that's not possible.
+    pub(crate) fn normalized<R: Rng + ?Sized>(&self, rng: &mut R) -> Self {
+        let ret = Self {
+            position: self.position / self.position.len(),
+            radius: self.radius,
+        };
+        if ret.is_valid() {
+            ret
+        } else {
+            Self::random_on(T::one(), rng)
+        }
+    }
+
+    /// This point projected onto its sphere. A point at the origin can't be projected and stays
+    /// invalid.
+    pub(crate) fn projected(self) -> Self {
+        Self {
+            position: self.position * (self.radius / self.position.len()),
+            radius: self.radius,
+        }
+    }
+
+    /// Checks whether the `SphericalVector` is valid.
+    ///
+    /// In this case, valid means none of the components are NaN or Inf, the position isn't zero,
+    /// and the radius is greater than zero.
+    pub(crate) fn is_valid(&self) -> bool {
+        !self.position.is_invalid()
+            && self.position.len() > T::zero()
+            && is_valid_radius(self.radius)
+    }
+}
+
+//
+// **** Trait Implementations ****
+//
+
+impl<const N: usize, T> TryFrom<SphericalVectorRepr<N, T>> for SphericalVector<N, T>
+where
+    T: Real,
+{
+    type Error = CoordinateError;
+
+    /// Validate a deserialized point. The position validates itself.
+    fn try_from(value: SphericalVectorRepr<N, T>) -> Result<Self, Self::Error> {
+        if value.model != CoordinateModel::Spherical {
+            return Err(CoordinateError::ModelMismatch);
+        }
+        if !is_valid_radius(value.radius) {
+            return Err(CoordinateError::InvalidRadius);
+        }
+        if (value.position.len() - value.radius).abs()
+            > value.radius * T::from_f64(SURFACE_TOLERANCE)
+        {
+            return Err(CoordinateError::OffSphere);
+        }
+        Ok(Self {
+            position: value.position,
+            radius: value.radius,
+        })
+    }
+}
+
+impl<const N: usize, T> Add for SphericalVector<N, T>
+where
+    T: Real,
+{
+    type Output = Self;
+
+    /// Add the positions of two vectors.
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            position: self.position + rhs.position,
+            radius: self.radius,
+        }
+    }
+}
+
+impl<const N: usize, T> Sub for SphericalVector<N, T>
+where
+    T: Real,
+{
+    type Output = Self;
+
+    /// Subtract the positions of two vectors.
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            position: self.position - rhs.position,
+            radius: self.radius,
+        }
+    }
+}
+
+impl<const N: usize, T> Mul<T> for SphericalVector<N, T>
+where
+    T: Real,
+{
+    type Output = Self;
+
+    /// Scale the position of a vector.
+    fn mul(self, rhs: T) -> Self::Output {
+        Self {
+            position: self.position * rhs,
+            radius: self.radius,
+        }
+    }
+}
+
+//
+// **** Functions ****
+//
+
+fn is_valid_radius<T: Real>(radius: T) -> bool {
+    radius.is_finite() && radius > T::zero()
+}
+
+//
+// **** Tests ****
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_new() {
+        let a = SphericalVector::<3>::new([3.0, 4.0, 0.0], 10.0).expect("valid point rejected");
+        assert_approx_eq!(a.position.len(), 10.0);
+        assert_approx_eq!(a.position[0], 6.0);
+        assert_approx_eq!(a.radius(), 10.0);
+
+        assert_eq!(
+            SphericalVector::<3>::new([0.0, 0.0, 0.0], 10.0),
+            Err(CoordinateError::OffSphere)
+        );
+        assert_eq!(
+            SphericalVector::<3>::new([1.0, 0.0, 0.0], -10.0),
+            Err(CoordinateError::InvalidRadius)
+        );
+        assert_eq!(
+            SphericalVector::<3>::random_with_radius(f64::NAN),
+            Err(CoordinateError::InvalidRadius)
+        );
+    }
+
+    #[test]
+    fn test_distance() {
+        let a = SphericalVector::<3>::new([1.0, 0.0, 0.0], 100.0).expect("valid point rejected");
+        let b = SphericalVector::<3>::new([0.0, 1.0, 0.0], 100.0).expect("valid point rejected");
+        let c = SphericalVector::<3>::new([-1.0, 0.0, 0.0], 100.0).expect("valid point rejected");
+
+        // a quarter and half way around
+        let pi = f64::acos(-1.0);
+        assert_approx_eq!(a.distance(&b), 50.0 * pi, 1e-3);
+        assert_approx_eq!(a.distance(&c), 100.0 * pi, 1e-3);
+        assert_approx_eq!(a.distance(&a), 0.0);
+    }
+
+    #[test]
+    fn test_moved_away() {
+        let a = SphericalVector::<3>::new([1.0, 0.0, 0.0], 100.0).expect("valid point rejected");
+        let b = SphericalVector::<3>::new([0.0, 1.0, 0.0], 100.0).expect("valid point rejected");
+
+        // moving toward b by the whole distance ends up at b
+        let c = a.moved_away(&b, -a.distance(&b), &mut thread_rng());
+        assert_approx_eq!(c.distance(&b), 0.0, 1e-3);
+        assert_approx_eq!(c.position.len(), 100.0, 1e-3);
+
+        // moving away from a coincident point picks some direction
+        let d = a.moved_away(&a, 10.0, &mut thread_rng());
+        assert_approx_eq!(d.distance(&a), 10.0, 1e-3);
+    }
+
+    #[test]
+    fn test_serde() {
+        let a = SphericalVector::<3>::new([0.0, 0.0, 80.0], 80.0).expect("valid point rejected");
+        let s = serde_json::to_string(&a).expect("serialization failed");
+        assert_eq!(
+            s,
+            "{\"model\":\"spherical\",\"position\":[0.0,0.0,80.0],\"radius\":80.0}"
+        );
+        let b: SphericalVector<3> = serde_json::from_str(&s).expect("deserialization failed");
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_deserialize_invalid() {
+        let parse = |s| serde_json::from_str::<SphericalVector<3>>(s).map_err(|e| e.to_string());
+
+        // off the sphere
+        let e = parse("{\"model\":\"spherical\",\"position\":[0.0,0.0,8.0],\"radius\":80.0}");
+        assert!(e.expect_err("invalid point accepted").contains("sphere"));
+
+        // wrong or missing model
+        let e = parse("{\"model\":\"hyperbolic\",\"position\":[0.0,0.0,80.0],\"radius\":80.0}");
+        assert!(e.expect_err("invalid point accepted").contains("model"));
+        assert!(parse("{\"position\":[0.0,0.0,80.0],\"radius\":80.0}").is_err());
+    }
+}
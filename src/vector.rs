@@ -10,6 +10,8 @@ use serde_with::serde_as;
 
 use core::ops::{Add, Div, Index, Mul, Sub};
 
+use crate::error::CoordinateError;
+
 //
 // **** Structs ****
 //
@@ -21,6 +23,7 @@ use core::ops::{Add, Div, Index, Mul, Sub};
 /// - `N`: the number of dimensions (i.e. 2 for 2D vectors, 3 for 3D, etc)
 #[serde_as]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "VectorRepr<T, N>")]
 pub struct Vector<T, const N: usize>
 where
     T: Float + Serialize + for<'d> Deserialize<'d>,
@@ -30,6 +33,18 @@ where
     inner: [T; N],
 }
 
+/// Unvalidated wire representation of a [`Vector`], only used for deserialization.
+#[serde_as]
+#[derive(Deserialize)]
+struct VectorRepr<T, const N: usize>
+where
+    T: Float + Serialize + for<'d> Deserialize<'d>,
+{
+    #[serde(rename = "position")]
+    #[serde_as(as = "[_; N]")]
+    inner: [T; N],
+}
+
 //
 // **** Implementations ****
 //
@@ -44,6 +59,19 @@ where
         self.inner.iter().fold(T::zero(), |acc, x| acc.hypot(*x))
     }
 
+    /// The components of the vector as an array.
+    pub(crate) const fn to_array(self) -> [T; N] {
+        self.inner
+    }
+
+    /// Compute the dot product of two vectors.
+    pub(crate) fn dot(&self, rhs: &Self) -> T {
+        self.inner
+            .iter()
+            .zip(rhs.inner.iter())
+            .fold(T::zero(), |acc, (a, b)| a.mul_add(*b, acc))
+    }
+
     /// Checks whether the `Vector` is invalid.
     ///
     /// In this case, valid means tnone of the components are NaN or Inf.
@@ -78,6 +106,23 @@ where
     }
 }
 
+impl<T, const N: usize> TryFrom<VectorRepr<T, N>> for Vector<T, N>
+where
+    T: Float + Serialize + for<'d> Deserialize<'d>,
+{
+    type Error = CoordinateError;
+
+    /// Validate a deserialized vector, rejecting NaN or Inf components.
+    fn try_from(value: VectorRepr<T, N>) -> Result<Self, Self::Error> {
+        let ret = Self { inner: value.inner };
+        if ret.is_invalid() {
+            Err(CoordinateError::NonFinitePosition)
+        } else {
+            Ok(ret)
+        }
+    }
+}
+
 impl<T, const N: usize> Add for Vector<T, N>
 where
     T: Float + Serialize + for<'d> Deserialize<'d>,
@@ -282,6 +327,14 @@ mod tests {
         assert_approx_eq!(b.len(), 37.416_57, 0.0001);
     }
 
+    #[test]
+    fn test_dot() {
+        let a = Vector::<f32, 3>::from([1.0, 2.0, 3.0]);
+        let b = Vector::<f32, 3>::from([4.0, -5.0, 6.0]);
+        assert_approx_eq!(a.dot(&b), 12.0);
+        assert_approx_eq!(a.dot(&a), a.len().powi(2), 0.0001);
+    }
+
     #[test]
     fn test_serde() {
         let a = Vector::<f32, 3>::from([1.0, 2.0, 3.0]);
@@ -291,5 +344,23 @@ mod tests {
             "{\"position\":[1.0,2.0,3.0]}"
         );
         assert!(s.is_ok());
+
+        let b: Vector<f32, 3> = serde_json::from_str("{\"position\":[1.0,2.0,3.0]}")
+            .expect("deserialization failed in test");
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_deserialize_invalid() {
+        let repr = VectorRepr::<f32, 2> {
+            inner: [1.0, f32::NAN],
+        };
+        assert_eq!(
+            Vector::try_from(repr),
+            Err(CoordinateError::NonFinitePosition)
+        );
+
+        // wrong dimensionality is rejected too
+        assert!(serde_json::from_str::<Vector<f32, 3>>("{\"position\":[1.0,2.0]}").is_err());
     }
 }
@@ -1,6 +1,8 @@
 // This is just for basic interface and happy path testing. It doesn't do anything other than make
 // sure the expected public interface exists and works.
 
+#![cfg(feature = "std")]
+
 use array_init::array_init;
 use core::time::Duration;
 use vivaldi_nc::NetworkCoordinate;
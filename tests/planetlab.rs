@@ -0,0 +1,98 @@
+// Embeds part of a PlanetLab RTT matrix in each of the latency spaces, and makes sure the
+// coordinates end up predicting the measured RTTs reasonably well.
+
+#![cfg(feature = "datasets")]
+
+use core::time::Duration;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use vivaldi_nc::{
+    datasets::{DatasetFormat, LatencyMatrix},
+    metrics::AccuracyReport,
+    HeightVector, HyperbolicVector, LatencySpace, NetworkCoordinate, SphericalVector,
+    VivaldiConfig,
+};
+
+const NUM_NODES: usize = 100;
+const NUM_ROUNDS: usize = 1000;
+const SEED: u64 = 42;
+
+/// Loads the top left `NUM_NODES` x `NUM_NODES` corner of the first PlanetLab matrix.
+fn load_planetlab() -> Vec<Vec<Option<Duration>>> {
+    let matrix = LatencyMatrix::load(
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/examples/NetLatency-Data/PlanetLab/PlanetLabData_1"
+        ),
+        DatasetFormat::PlanetLab,
+    )
+    .expect("unable to load PlanetLab data");
+    matrix
+        .rows()
+        .take(NUM_NODES)
+        .map(|row| row[..NUM_NODES].to_vec())
+        .collect()
+}
+
+/// Runs Vivaldi over the whole matrix and returns the median relative error of the estimates.
+fn median_relative_error<const N: usize, S: LatencySpace<f64>>(
+    mut nc: Vec<NetworkCoordinate<N, f64, S>>,
+    rng: &mut StdRng,
+) -> f64 {
+    let rtts = load_planetlab();
+    let config = VivaldiConfig::default();
+    for round in 0..NUM_ROUNDS {
+        for i in 0..NUM_NODES {
+            // every node hears from a different peer each round
+            let j = (i + round * 7 + 1) % NUM_NODES;
+            let Some(rtt) = rtts[i][j].filter(|_| i != j) else {
+                continue;
+            };
+            let other = nc[j].clone();
+            // bad samples are simply skipped, just like `update()` does
+            let _ = nc[i].try_update_with_rng(&other, rtt, &config, rng);
+        }
+    }
+
+    AccuracyReport::new(&nc, &rtts)
+        .expect("no measured RTTs")
+        .median_relative_error()
+}
+
+/// Creates `NUM_NODES` seeded random coordinates, and runs Vivaldi on them.
+fn seeded_median_relative_error<const N: usize, S: LatencySpace<f64>>() -> f64 {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let nc = (0..NUM_NODES)
+        .map(|_| NetworkCoordinate::<N, f64, S>::new_with_rng(&mut rng))
+        .collect();
+    median_relative_error(nc, &mut rng)
+}
+
+#[test]
+fn planetlab_height_vector() {
+    assert!(seeded_median_relative_error::<2, HeightVector<2>>() < 0.25);
+}
+
+#[test]
+fn planetlab_hyperbolic() {
+    assert!(seeded_median_relative_error::<2, HyperbolicVector<2>>() < 0.25);
+}
+
+#[test]
+fn planetlab_hyperbolic_curvature() {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let nc = (0..NUM_NODES)
+        .map(|_| {
+            let position = [rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)];
+            let point = HyperbolicVector::new(position, 1e-3).expect("valid curvature rejected");
+            NetworkCoordinate::<2, f64, HyperbolicVector<2>>::from_point(point)
+                .expect("valid point rejected")
+        })
+        .collect();
+    assert!(median_relative_error(nc, &mut rng) < 0.3);
+}
+
+#[test]
+fn planetlab_spherical() {
+    assert!(seeded_median_relative_error::<3, SphericalVector<3>>() < 0.25);
+}